@@ -1,4 +1,12 @@
 use hlt::entity::{Entity, Position};
+use hlt::game_map::GameMap;
+
+/// An entity that may block a path, ships and planets have separate ids.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Obstacle {
+    Ship(i32),
+    Planet(i32),
+}
 
 /// Test whether a line segment and circle intersect.
 pub fn intersect_segment_circle<E: Entity, F: Entity, G: Entity>(
@@ -23,13 +31,9 @@ pub fn intersect_segment_circle<E: Entity, F: Entity, G: Entity>(
         return start.distance_with(circle) <= circle.radius() + fudge;
     }
 
-    let &t = [-b / (2.0 * a), 1.0]
-        .iter()
-        .min_by(|x, y| x.partial_cmp(y).unwrap())
-        .unwrap();
-    if t < 0.0 {
-        return false;
-    }
+    // The closest point lies on the segment, a circle behind the start is
+    // closest to the start itself.
+    let t = (-b / (2.0 * a)).clamp(0.0, 1.0);
 
     let closest_x = start_x + dx * t;
     let closest_y = start_y + dy * t;
@@ -37,3 +41,216 @@ pub fn intersect_segment_circle<E: Entity, F: Entity, G: Entity>(
 
     closest_distance <= circle.radius() + fudge
 }
+
+/// Time in `[0, 1]` at which two points moving at constant velocity during a
+/// turn are closest, and their distance at that time.
+pub fn closest_approach(
+    a_start: Position,
+    (a_vx, a_vy): (f64, f64),
+    b_start: Position,
+    (b_vx, b_vy): (f64, f64),
+) -> (f64, f64) {
+    let Position(a_x, a_y) = a_start;
+    let Position(b_x, b_y) = b_start;
+    let (dx, dy) = (b_x - a_x, b_y - a_y);
+    let (dvx, dvy) = (b_vx - a_vx, b_vy - a_vy);
+
+    let speed = dvx * dvx + dvy * dvy;
+    let t = if speed == 0.0 {
+        0.0
+    } else {
+        (-(dx * dvx + dy * dvy) / speed).clamp(0.0, 1.0)
+    };
+    let (closest_x, closest_y) = (dx + dvx * t, dy + dvy * t);
+
+    (t, f64::sqrt(closest_x * closest_x + closest_y * closest_y))
+}
+
+/// Test whether two circles moving at constant velocity during a turn come
+/// within `distance` of each other between the start and the end of it.
+pub fn intersect_moving_circles(
+    a_start: Position,
+    a_velocity: (f64, f64),
+    b_start: Position,
+    b_velocity: (f64, f64),
+    distance: f64,
+) -> bool {
+    closest_approach(a_start, a_velocity, b_start, b_velocity).1 <= distance
+}
+
+/// Earliest time in `[0, 1]` at which two circles whose radii sum to
+/// `radius` touch while moving at constant velocity.
+pub fn collision_time(
+    a_start: Position,
+    (a_vx, a_vy): (f64, f64),
+    b_start: Position,
+    (b_vx, b_vy): (f64, f64),
+    radius: f64,
+) -> Option<f64> {
+    let Position(a_x, a_y) = a_start;
+    let Position(b_x, b_y) = b_start;
+    let (dx, dy) = (b_x - a_x, b_y - a_y);
+    let (dvx, dvy) = (b_vx - a_vx, b_vy - a_vy);
+
+    let a = dvx * dvx + dvy * dvy;
+    let b = 2.0 * (dx * dvx + dy * dvy);
+    let c = dx * dx + dy * dy - radius * radius;
+
+    if a == 0.0 {
+        return None;
+    }
+    if c <= 0.0 {
+        // Already touching; only counts if they keep closing in.
+        return if b < 0.0 { Some(0.0) } else { None };
+    }
+
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let t = (-b - discriminant.sqrt()) / (2.0 * a);
+    if (0.0..=1.0).contains(&t) {
+        Some(t)
+    } else {
+        None
+    }
+}
+
+/// Distance from a point to the closest point of a segment.
+#[allow(dead_code)]
+pub fn point_segment_distance<E: Entity, F: Entity, G: Entity>(point: &E, start: &F, end: &G) -> f64 {
+    let Position(x, y) = point.position();
+    let Position(start_x, start_y) = start.position();
+    let Position(end_x, end_y) = end.position();
+    let (dx, dy) = (end_x - start_x, end_y - start_y);
+
+    let length = dx * dx + dy * dy;
+    if length == 0.0 {
+        return point.distance_with(start);
+    }
+    let t = (((x - start_x) * dx + (y - start_y) * dy) / length).clamp(0.0, 1.0);
+    point.distance_with(&Position(start_x + dx * t, start_y + dy * t))
+}
+
+/// Shortest distance between two segments, 0 when they cross.
+#[allow(dead_code)]
+pub fn segment_distance<A: Entity, B: Entity, C: Entity, D: Entity>(
+    a_start: &A,
+    a_end: &B,
+    b_start: &C,
+    b_end: &D,
+) -> f64 {
+    let (p1, p2) = (a_start.position(), a_end.position());
+    let (q1, q2) = (b_start.position(), b_end.position());
+
+    let d1 = cross(&q1, &q2, &p1);
+    let d2 = cross(&q1, &q2, &p2);
+    let d3 = cross(&p1, &p2, &q1);
+    let d4 = cross(&p1, &p2, &q2);
+    if d1 * d2 < 0.0 && d3 * d4 < 0.0 {
+        return 0.0;
+    }
+
+    // Otherwise the closest pair of points includes an endpoint.
+    point_segment_distance(&p1, &q1, &q2)
+        .min(point_segment_distance(&p2, &q1, &q2))
+        .min(point_segment_distance(&q1, &p1, &p2))
+        .min(point_segment_distance(&q2, &p1, &p2))
+}
+
+/// Which side of the line from `a` to `b` the point `c` lies on.
+fn cross(&Position(ax, ay): &Position, &Position(bx, by): &Position, &Position(cx, cy): &Position) -> f64 {
+    (bx - ax) * (cy - ay) - (by - ay) * (cx - ax)
+}
+
+/// Test whether nothing but the `ignore`d entities lies within `fudge` of the
+/// segment from `start` to `end`.
+pub fn line_of_sight<E: Entity, F: Entity>(
+    game_map: &GameMap,
+    start: &E,
+    end: &F,
+    fudge: f64,
+    ignore: &[Obstacle],
+) -> bool {
+    let planet_blocks = game_map
+        .planets_along(start, end, fudge)
+        .iter()
+        .any(|planet| !ignore.contains(&Obstacle::Planet(planet.id)));
+    if planet_blocks {
+        return false;
+    }
+    !game_map
+        .ships_along(start, end, fudge)
+        .iter()
+        .any(|ship| !ignore.contains(&Obstacle::Ship(ship.id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use hlt::entity::Position;
+    use hlt::fixture::{game_map, planet, ship, state};
+    use super::*;
+
+    #[test]
+    fn test_segment_circle() {
+        let (start, end) = (Position(0.0, 0.0), Position(10.0, 0.0));
+        assert!(intersect_segment_circle(&start, &end, &Position(5.0, 1.0), 1.0));
+        assert!(!intersect_segment_circle(&start, &end, &Position(5.0, 1.5), 1.0));
+        // Behind the start the distance to the start counts.
+        assert!(intersect_segment_circle(&start, &end, &Position(-1.0, 0.0), 1.5));
+        assert!(!intersect_segment_circle(&start, &end, &Position(-2.0, 0.0), 1.5));
+        // Past the end the distance to the end counts.
+        assert!(intersect_segment_circle(&start, &end, &Position(11.0, 0.0), 1.5));
+    }
+
+    #[test]
+    fn test_closest_approach() {
+        let origin = Position(0.0, 0.0);
+        // Passing at a distance of 2 half way through the turn.
+        assert_eq!((0.5, 2.0), closest_approach(origin, (1.0, 0.0), Position(1.0, 2.0), (-1.0, 0.0)));
+        // Still closing in when the turn ends.
+        assert_eq!((1.0, 8.0), closest_approach(origin, (1.0, 0.0), Position(10.0, 0.0), (-1.0, 0.0)));
+        // Moving apart, closest at the start.
+        assert_eq!((0.0, 5.0), closest_approach(origin, (-1.0, 0.0), Position(3.0, 4.0), (1.0, 0.0)));
+        assert!(intersect_moving_circles(origin, (1.0, 0.0), Position(1.0, 2.0), (-1.0, 0.0), 2.0));
+        assert!(!intersect_moving_circles(origin, (1.0, 0.0), Position(1.0, 2.0), (-1.0, 0.0), 1.9));
+    }
+
+    #[test]
+    fn test_collision_time() {
+        let origin = Position(0.0, 0.0);
+        let target = Position(6.0, 0.0);
+        // |6 - 7t| = 1 first at t = 5 / 7.
+        let t = collision_time(origin, (7.0, 0.0), target, (0.0, 0.0), 1.0).unwrap();
+        assert!((t - 5.0 / 7.0).abs() < 1e-9);
+        // |6 - 4t| = 1 only at t = 1.25, after the turn.
+        assert_eq!(None, collision_time(origin, (4.0, 0.0), target, (0.0, 0.0), 1.0));
+        assert_eq!(None, collision_time(origin, (0.0, 0.0), target, (0.0, 0.0), 1.0));
+    }
+
+    #[test]
+    fn test_segment_distance() {
+        let (start, end) = (Position(0.0, 0.0), Position(4.0, 0.0));
+        assert_eq!(0.0, segment_distance(&start, &end, &Position(2.0, -1.0), &Position(2.0, 1.0)));
+        assert_eq!(3.0, segment_distance(&start, &end, &Position(0.0, 3.0), &Position(4.0, 3.0)));
+        assert_eq!(5f64.sqrt(), segment_distance(&start, &end, &Position(6.0, 1.0), &Position(8.0, 1.0)));
+        assert_eq!(1.0, segment_distance(&start, &end, &Position(2.0, 1.0), &Position(2.0, 5.0)));
+        assert_eq!(2.0, point_segment_distance(&Position(-2.0, 0.0), &start, &end));
+    }
+
+    #[test]
+    fn test_line_of_sight() {
+        // Our ship 0 at (10, 10), enemy ship 1 at (60, 10) and planet 0 of
+        // radius 5 at (30, 10).
+        let game_map = game_map(state(
+            vec![vec![ship(0, 10.0, 10.0)], vec![ship(1, 60.0, 10.0)]],
+            vec![planet(0, 30.0, 10.0, 5.0)],
+        ));
+        let (start, end) = (Position(12.0, 10.0), Position(60.0, 10.0));
+
+        assert!(!line_of_sight(&game_map, &start, &end, 0.0, &[]));
+        assert!(!line_of_sight(&game_map, &start, &end, 0.0, &[Obstacle::Planet(0)]));
+        assert!(line_of_sight(&game_map, &start, &end, 0.0, &[Obstacle::Planet(0), Obstacle::Ship(1)]));
+        assert!(line_of_sight(&game_map, &start, &Position(12.0, 40.0), 0.0, &[]));
+    }
+}
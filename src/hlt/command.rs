@@ -1,6 +1,11 @@
+use std::collections::HashSet;
 use std::fmt::{Display, Formatter, Result};
+use super::constants::MAX_SPEED;
+use super::entity::DockingStatus;
+use super::game_map::GameMap;
+use super::parse::{Decodable, ParseError, ParseResult, Tokens};
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Command {
     Dock(i32, i32),
     Undock(i32),
@@ -17,6 +22,69 @@ impl Command {
             Command::Nop => "".to_string(),
         }
     }
+
+    /// Parse a single encoded command such as `t 3 7 90`.
+    #[allow(dead_code)]
+    pub fn decode(command: &str) -> ParseResult<Command> {
+        let mut tokens = Tokens::from_line(command);
+        let result = Command::parse(&mut tokens)?;
+        let index = tokens.index();
+        match tokens.next_token("end of command") {
+            Ok(extra) => Err(ParseError::new(index, "end of command", Some(extra))),
+            Err(_) => Ok(result),
+        }
+    }
+
+    /// The ship this command is issued to.
+    pub fn ship_id(&self) -> Option<i32> {
+        match *self {
+            Command::Dock(s, _) | Command::Undock(s) | Command::Thrust(s, _, _) => Some(s),
+            Command::Nop => None,
+        }
+    }
+}
+
+/// Render a turn's commands as the single line sent to the engine, without
+/// the trailing newline. Commands are separated by a space and `Nop`s, which
+/// have no wire representation, are left out.
+pub fn encode_commands(commands: &[Command]) -> String {
+    commands
+        .iter()
+        .filter(|command| **command != Command::Nop)
+        .map(Command::encode)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Read a turn's line of commands the way the engine does, the inverse of
+/// `encode_commands`.
+pub fn decode_commands(line: &str) -> ParseResult<Vec<Command>> {
+    let count = line.split_whitespace().count();
+    let mut tokens = Tokens::from_line(line);
+    let mut commands = Vec::new();
+    while tokens.index() < count {
+        commands.push(Command::parse(&mut tokens)?);
+    }
+    Ok(commands)
+}
+
+impl Decodable for Command {
+    fn parse<'a, I>(tokens: &mut Tokens<I>) -> ParseResult<Self>
+    where
+        I: Iterator<Item = &'a str>,
+    {
+        let index = tokens.index();
+        match tokens.next_token("command")? {
+            "d" => Ok(Command::Dock(i32::parse(tokens)?, i32::parse(tokens)?)),
+            "u" => Ok(Command::Undock(i32::parse(tokens)?)),
+            "t" => Ok(Command::Thrust(
+                i32::parse(tokens)?,
+                i32::parse(tokens)?,
+                i32::parse(tokens)?,
+            )),
+            other => Err(ParseError::new(index, "command", Some(other))),
+        }
+    }
 }
 
 impl Display for Command {
@@ -31,9 +99,116 @@ impl Display for Command {
     }
 }
 
+/// Reasons the engine would reject a command.
+#[derive(Debug, PartialEq)]
+pub enum CommandError {
+    /// The ship was already given a command this turn.
+    DuplicateShip(i32),
+    /// The ship does not exist or belongs to another player.
+    NotOwned(i32),
+    /// Thrust magnitude outside `0..=MAX_SPEED`.
+    InvalidMagnitude(i32, i32),
+    /// Thrust angle outside `0..360`.
+    InvalidAngle(i32, i32),
+    /// Docking requires the ship to be undocked.
+    NotUndocked(i32),
+    /// Undocking requires the ship to be docked.
+    NotDocked(i32),
+}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match *self {
+            CommandError::DuplicateShip(s) => write!(f, "ship {} has more than one command", s),
+            CommandError::NotOwned(s) => write!(f, "ship {} is not ours", s),
+            CommandError::InvalidMagnitude(s, m) => {
+                write!(f, "ship {} thrust magnitude {} exceeds 0..={}", s, m, MAX_SPEED)
+            }
+            CommandError::InvalidAngle(s, a) => write!(f, "ship {} thrust angle {} is not in 0..360", s, a),
+            CommandError::NotUndocked(s) => write!(f, "ship {} can't dock while docked", s),
+            CommandError::NotDocked(s) => write!(f, "ship {} can't undock while undocked", s),
+        }
+    }
+}
+
+/// The commands issued during a single turn.
+#[derive(Debug, Default)]
+pub struct CommandQueue {
+    commands: Vec<Command>,
+}
+
+impl CommandQueue {
+    pub fn new() -> Self {
+        CommandQueue { commands: Vec::new() }
+    }
+
+    pub fn push(&mut self, command: Command) {
+        self.commands.push(command)
+    }
+
+    pub fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    pub fn clear(&mut self) {
+        self.commands.clear()
+    }
+
+    /// Check every command against the rules of the engine, dropping the ones
+    /// it would reject and returning why. The first valid command for a ship
+    /// wins.
+    pub fn validate(&mut self, game_map: &GameMap) -> Vec<CommandError> {
+        let mut errors = Vec::new();
+        let mut seen = HashSet::new();
+        let me = game_map.me();
+
+        self.commands.retain(|command| {
+            let ship_id = match command.ship_id() {
+                Some(ship_id) => ship_id,
+                None => return true,
+            };
+            let error = if seen.contains(&ship_id) {
+                Some(CommandError::DuplicateShip(ship_id))
+            } else {
+                match me.get_ship(ship_id) {
+                    None => Some(CommandError::NotOwned(ship_id)),
+                    Some(ship) => match *command {
+                        Command::Thrust(_, m, _) if !(0..=MAX_SPEED).contains(&m) => {
+                            Some(CommandError::InvalidMagnitude(ship_id, m))
+                        }
+                        Command::Thrust(_, _, a) if !(0..360).contains(&a) => {
+                            Some(CommandError::InvalidAngle(ship_id, a))
+                        }
+                        Command::Dock(_, _) if ship.docking_status != DockingStatus::UNDOCKED => {
+                            Some(CommandError::NotUndocked(ship_id))
+                        }
+                        Command::Undock(_) if ship.docking_status != DockingStatus::DOCKED => {
+                            Some(CommandError::NotDocked(ship_id))
+                        }
+                        _ => None,
+                    },
+                }
+            };
+
+            match error {
+                Some(error) => {
+                    errors.push(error);
+                    false
+                }
+                None => {
+                    seen.insert(ship_id);
+                    true
+                }
+            }
+        });
+        errors
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::Command;
+    use super::{decode_commands, encode_commands, Command, CommandError, CommandQueue};
+    use hlt::fixture::{docked, game_map, ship, state};
 
     #[test]
     fn test_thing() {
@@ -41,4 +216,80 @@ mod tests {
         assert_eq!("t 3 9 4", Command::Thrust(3, 9, 4).encode());
         assert_eq!("u 3", Command::Undock(3).encode());
     }
+
+    #[test]
+    fn test_encode_commands() {
+        assert_eq!("", encode_commands(&[]));
+        assert_eq!("", encode_commands(&[Command::Nop]));
+        assert_eq!(
+            "t 1 7 90 d 2 4 u 3",
+            encode_commands(&[
+                Command::Thrust(1, 7, 90),
+                Command::Nop,
+                Command::Dock(2, 4),
+                Command::Undock(3),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_decode_commands() {
+        let commands = [Command::Thrust(1, 7, 90), Command::Dock(2, 4), Command::Undock(3)];
+        assert_eq!(commands.to_vec(), decode_commands(&encode_commands(&commands)).unwrap());
+        assert_eq!(Vec::<Command>::new(), decode_commands("").unwrap());
+        assert_eq!(5, decode_commands("u 3 t 1 7").unwrap_err().index);
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(Command::Dock(10, 4), Command::decode("d 10 4").unwrap());
+        assert_eq!(Command::Thrust(3, 9, 4), Command::decode("t 3 9 4").unwrap());
+        assert_eq!(Command::Undock(3), Command::decode("u 3").unwrap());
+        assert_eq!(4, Command::decode("t 3 9 4 1").unwrap_err().index);
+        assert_eq!(Some("x".to_string()), Command::decode("x 3").unwrap_err().found);
+        assert!(Command::decode("t 3 9").is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        // Player 0 owns undocked ship 1 and docked ship 2, player 1 owns ship 3.
+        let game_map = game_map(state(
+            vec![vec![ship(1, 10.0, 10.0), docked(ship(2, 20.0, 20.0), 0)], vec![ship(3, 50.0, 50.0)]],
+            vec![],
+        ));
+
+        let mut queue = CommandQueue::new();
+        queue.push(Command::Thrust(1, 7, 90));
+        queue.push(Command::Thrust(1, 7, 90));
+        queue.push(Command::Dock(2, 0));
+        queue.push(Command::Undock(2));
+        queue.push(Command::Thrust(3, 7, 90));
+        queue.push(Command::Nop);
+
+        let errors = queue.validate(&game_map);
+        assert_eq!(
+            vec![
+                CommandError::DuplicateShip(1),
+                CommandError::NotUndocked(2),
+                CommandError::NotOwned(3),
+            ],
+            errors
+        );
+        // A rejected command doesn't stop a later valid one for the same ship.
+        assert_eq!(
+            &[Command::Thrust(1, 7, 90), Command::Undock(2), Command::Nop],
+            queue.commands()
+        );
+
+        let mut queue = CommandQueue::new();
+        queue.push(Command::Thrust(1, 8, 90));
+        queue.push(Command::Thrust(2, 7, 360));
+        assert_eq!(
+            vec![
+                CommandError::InvalidMagnitude(1, 8),
+                CommandError::InvalidAngle(2, 360),
+            ],
+            queue.validate(&game_map)
+        );
+    }
 }
@@ -23,3 +23,8 @@ pub const DOCK_TURNS: i32 = 5;
 pub const BASE_PRODUCTIVITY: i32 = 6;
 // Distance from the planets edge at which new ships are created
 pub const SPAWN_RADIUS: f64 = 2.0;
+// Production needed by a planet to spawn a new ship
+pub const SHIP_COST: i32 = 72;
+// Damage dealt by an exploding planet at its surface, falling off linearly to
+// nothing at EXPLOSION_RADIUS
+pub const EXPLOSION_DAMAGE: i32 = 5 * MAX_SHIP_HEALTH;
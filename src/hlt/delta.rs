@@ -0,0 +1,172 @@
+use hlt::entity::{DockingStatus, GameState};
+
+/// Ships that appeared or disappeared for one player.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct ShipChanges {
+    pub player: i32,
+    pub spawned: Vec<i32>,
+    pub destroyed: Vec<i32>,
+}
+
+/// A planet that changed hands, `None` meaning unowned.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct OwnerChange {
+    pub planet: i32,
+    pub from: Option<i32>,
+    pub to: Option<i32>,
+}
+
+/// Hit points an entity lost since the previous turn.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct HpLoss {
+    pub id: i32,
+    pub lost: i32,
+}
+
+/// A ship whose docking status changed.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct DockingChange {
+    pub ship: i32,
+    pub from: DockingStatus,
+    pub to: DockingStatus,
+}
+
+/// What changed from one frame to the next. Entities are listed in the order
+/// the engine sends them.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct TurnDelta {
+    /// One entry per player of the new frame.
+    pub ships: Vec<ShipChanges>,
+    pub owners: Vec<OwnerChange>,
+    /// Planets present in the previous frame but gone from this one.
+    pub destroyed_planets: Vec<i32>,
+    /// Damage taken by ships that survived.
+    pub ship_hp: Vec<HpLoss>,
+    /// Damage taken by planets that survived.
+    pub planet_hp: Vec<HpLoss>,
+    pub docking: Vec<DockingChange>,
+}
+
+impl TurnDelta {
+    /// Compare two consecutive frames. Without a previous frame nothing
+    /// changed.
+    pub fn between(previous: Option<&GameState>, current: &GameState) -> Self {
+        let previous = match previous {
+            Some(previous) => previous,
+            None => return TurnDelta::default(),
+        };
+        let mut delta = TurnDelta::default();
+
+        for player in current.players.iter() {
+            let before = previous.players.iter().find(|p| p.id == player.id);
+            let mut changes = ShipChanges {
+                player: player.id,
+                ..ShipChanges::default()
+            };
+            for ship in player.all_ships().iter() {
+                match before.and_then(|p| p.get_ship(ship.id)) {
+                    Some(old) => {
+                        if ship.hp < old.hp {
+                            delta.ship_hp.push(HpLoss { id: ship.id, lost: old.hp - ship.hp });
+                        }
+                        if ship.docking_status != old.docking_status {
+                            delta.docking.push(DockingChange {
+                                ship: ship.id,
+                                from: old.docking_status,
+                                to: ship.docking_status,
+                            });
+                        }
+                    }
+                    None => changes.spawned.push(ship.id),
+                }
+            }
+            if let Some(before) = before {
+                changes.destroyed = before
+                    .all_ships()
+                    .iter()
+                    .filter(|ship| player.get_ship(ship.id).is_none())
+                    .map(|ship| ship.id)
+                    .collect();
+            }
+            delta.ships.push(changes);
+        }
+
+        for old in previous.planets.iter() {
+            let planet = match current.planets.iter().find(|p| p.id == old.id) {
+                Some(planet) if planet.hp > 0 => planet,
+                _ => {
+                    if old.hp > 0 {
+                        delta.destroyed_planets.push(old.id);
+                    }
+                    continue;
+                }
+            };
+            if planet.owner != old.owner {
+                delta.owners.push(OwnerChange {
+                    planet: planet.id,
+                    from: old.owner,
+                    to: planet.owner,
+                });
+            }
+            if planet.hp < old.hp {
+                delta.planet_hp.push(HpLoss { id: planet.id, lost: old.hp - planet.hp });
+            }
+        }
+        delta
+    }
+
+    /// Ship changes of a player.
+    #[allow(dead_code)]
+    pub fn player(&self, player_id: i32) -> Option<&ShipChanges> {
+        self.ships.iter().find(|changes| changes.player == player_id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ships.iter().all(|changes| changes.spawned.is_empty() && changes.destroyed.is_empty()) &&
+            self.owners.is_empty() && self.destroyed_planets.is_empty() &&
+            self.ship_hp.is_empty() && self.planet_hp.is_empty() && self.docking.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hlt::entity::{DockingStatus, GameState};
+    use hlt::parse::{Decodable, Tokens};
+    use super::{DockingChange, HpLoss, OwnerChange, TurnDelta};
+
+    fn state(frame: &str) -> GameState {
+        GameState::parse(&mut Tokens::from_line(frame)).unwrap()
+    }
+
+    #[test]
+    fn test_between() {
+        let before = state(
+            "2 0 2 0 10 10 255 0 0 0 0 0 0 1 20 20 255 0 0 0 0 0 0 \
+             1 1 5 50 50 255 0 0 0 0 0 0 \
+             2 0 30 30 1000 5 3 0 1000 0 0 0 1 70 70 900 5 3 0 1000 0 0 0",
+        );
+        // Ship 1 of player 0 dies, ship 3 spawns and ship 0 starts docking.
+        // Player 1 loses hp on ship 5 and takes planet 0, planet 1 blows up.
+        let after = state(
+            "2 0 2 0 10 10 255 0 0 1 0 0 0 3 12 12 255 0 0 0 0 0 0 \
+             1 1 5 50 50 200 0 0 0 0 0 0 \
+             1 0 30 30 990 5 3 0 1000 1 1 0",
+        );
+
+        assert!(TurnDelta::between(None, &after).is_empty());
+        assert!(TurnDelta::between(Some(&after), &after).is_empty());
+
+        let delta = TurnDelta::between(Some(&before), &after);
+        let mine = delta.player(0).unwrap();
+        assert_eq!((vec![3], vec![1]), (mine.spawned.clone(), mine.destroyed.clone()));
+        assert!(delta.player(1).unwrap().spawned.is_empty());
+        assert_eq!(vec![HpLoss { id: 5, lost: 55 }], delta.ship_hp);
+        assert_eq!(vec![HpLoss { id: 0, lost: 10 }], delta.planet_hp);
+        assert_eq!(vec![OwnerChange { planet: 0, from: None, to: Some(1) }], delta.owners);
+        assert_eq!(vec![1], delta.destroyed_planets);
+        assert_eq!(
+            vec![DockingChange { ship: 0, from: DockingStatus::UNDOCKED, to: DockingStatus::DOCKING }],
+            delta.docking
+        );
+    }
+}
@@ -0,0 +1,79 @@
+/// Counterpart of `Decodable`: writes a value in the engine's token format so
+/// that decoding the result yields the same value.
+pub trait Encodable {
+    fn encode(&self, tokens: &mut Vec<String>);
+
+    /// Encode the value as a single space separated line, without the
+    /// trailing newline.
+    fn to_line(&self) -> String {
+        let mut tokens = Vec::new();
+        self.encode(&mut tokens);
+        tokens.join(" ")
+    }
+}
+
+impl Encodable for f64 {
+    fn encode(&self, tokens: &mut Vec<String>) {
+        // `Display` for f64 prints the shortest string that parses back to
+        // the same value.
+        tokens.push(self.to_string())
+    }
+}
+
+impl Encodable for i32 {
+    fn encode(&self, tokens: &mut Vec<String>) {
+        tokens.push(self.to_string())
+    }
+}
+
+impl Encodable for usize {
+    fn encode(&self, tokens: &mut Vec<String>) {
+        tokens.push(self.to_string())
+    }
+}
+
+impl Encodable for bool {
+    fn encode(&self, tokens: &mut Vec<String>) {
+        tokens.push(if *self { "1" } else { "0" }.to_string())
+    }
+}
+
+impl<T: Encodable> Encodable for Box<[T]> {
+    fn encode(&self, tokens: &mut Vec<String>) {
+        self.len().encode(tokens);
+        for value in self.iter() {
+            value.encode(tokens);
+        }
+    }
+}
+
+/// The wire format always carries a value after the presence flag, so `None`
+/// is written as the default of `T`.
+impl<T: Encodable + Default> Encodable for Option<T> {
+    fn encode(&self, tokens: &mut Vec<String>) {
+        self.is_some().encode(tokens);
+        match *self {
+            Some(ref value) => value.encode(tokens),
+            None => T::default().encode(tokens),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Encodable;
+    use hlt::entity::GameState;
+    use hlt::parse::{Decodable, Tokens};
+
+    #[test]
+    fn test_round_trip() {
+        let line = "2 0 1 3 10.5 20 255 0 0 0 0 0 0 \
+                    1 1 4 1 2 64 0 0 2 7 3 1 \
+                    1 7 30 40.25 1000 5.5 3 0 1000 1 1 1 4";
+        let state = GameState::parse(&mut Tokens::from_line(line)).unwrap();
+        assert_eq!(line, state.to_line());
+
+        let decoded = GameState::parse(&mut Tokens::from_line(&state.to_line())).unwrap();
+        assert_eq!(format!("{:?}", state), format!("{:?}", decoded));
+    }
+}
@@ -1,7 +1,8 @@
 use std::fmt::{Display, Formatter, Result};
-use hlt::parse::Decodable;
+use hlt::parse::{Decodable, ParseError, ParseResult, Tokens};
+use hlt::encode::Encodable;
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone, Copy)]
 pub enum DockingStatus {
     UNDOCKED = 0,
     DOCKING = 1,
@@ -10,22 +11,31 @@ pub enum DockingStatus {
 }
 
 impl Decodable for DockingStatus {
-    fn parse<'a, I>(tokens: &mut I) -> Self
+    fn parse<'a, I>(tokens: &mut Tokens<I>) -> ParseResult<Self>
     where
         I: Iterator<Item = &'a str>,
     {
-
-        let raw = i32::parse(tokens);
+        let index = tokens.index();
+        let raw = i32::parse(tokens)?;
         match raw {
-            0 => DockingStatus::UNDOCKED,
-            1 => DockingStatus::DOCKING,
-            2 => DockingStatus::DOCKED,
-            3 => DockingStatus::UNDOCKING,
-            _ => panic!("Not a valid docking status: {:?}", raw),
+            0 => Ok(DockingStatus::UNDOCKED),
+            1 => Ok(DockingStatus::DOCKING),
+            2 => Ok(DockingStatus::DOCKED),
+            3 => Ok(DockingStatus::UNDOCKING),
+            _ => Err(ParseError::new(
+                index,
+                "DockingStatus",
+                Some(&raw.to_string()),
+            )),
         }
     }
 }
 
+impl Encodable for DockingStatus {
+    fn encode(&self, tokens: &mut Vec<String>) {
+        (*self as i32).encode(tokens)
+    }
+}
 
 impl Display for DockingStatus {
     fn fmt(&self, f: &mut Formatter) -> Result {
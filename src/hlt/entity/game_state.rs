@@ -1,21 +1,29 @@
 use hlt::entity::Planet;
 use hlt::player::Player;
-use hlt::parse::Decodable;
+use hlt::parse::{Decodable, ParseContext, ParseResult, Tokens};
+use hlt::encode::Encodable;
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct GameState {
     pub players: Box<[Player]>,
     pub planets: Box<[Planet]>,
 }
 
 impl Decodable for GameState {
-    fn parse<'a, I>(tokens: &mut I) -> Self
+    fn parse<'a, I>(tokens: &mut Tokens<I>) -> ParseResult<Self>
     where
         I: Iterator<Item = &'a str>,
     {
-        let players = Box::parse(tokens);
-        let planets = Box::parse(tokens);
+        let players = Box::parse(tokens).field("players")?;
+        let planets = Box::parse(tokens).field("planets")?;
 
-        Self { players, planets }
+        Ok(Self { players, planets })
+    }
+}
+
+impl Encodable for GameState {
+    fn encode(&self, tokens: &mut Vec<String>) {
+        self.players.encode(tokens);
+        self.planets.encode(tokens);
     }
 }
@@ -1,10 +1,12 @@
 use std::fmt::{Display, Formatter, Result};
+use hlt::constants::{EXPLOSION_DAMAGE, EXPLOSION_RADIUS};
 use hlt::entity::Position;
-use hlt::parse::Decodable;
+use hlt::parse::{Decodable, ParseContext, ParseResult, Tokens};
+use hlt::encode::Encodable;
 use hlt::entity::Entity;
 
 /// A planet on the game map.
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct Planet {
     pub id: i32,
     pub position: Position,
@@ -33,25 +35,36 @@ impl Planet {
     pub fn is_dead(&self) -> bool {
         self.hp <= 0
     }
+
+    /// Damage dealt to `target` when the planet explodes, falling off
+    /// linearly from the surface to `EXPLOSION_RADIUS`. Ships docked to the
+    /// planet are destroyed regardless.
+    pub fn explosion_damage<T: Entity>(&self, target: &T) -> i32 {
+        let distance = self.distance_with(target) - self.radius - target.radius();
+        if distance > EXPLOSION_RADIUS {
+            return 0;
+        }
+        let falloff = 1.0 - distance.max(0.0) / EXPLOSION_RADIUS;
+        (EXPLOSION_DAMAGE as f64 * falloff).ceil() as i32
+    }
 }
 
 impl Decodable for Planet {
-    fn parse<'a, I>(tokens: &mut I) -> Self
+    fn parse<'a, I>(tokens: &mut Tokens<I>) -> ParseResult<Self>
     where
         I: Iterator<Item = &'a str>,
     {
+        let id = i32::parse(tokens).field("id")?;
+        let position = Position::parse(tokens).field("position")?;
+        let hp = i32::parse(tokens).field("hp")?;
+        let radius = f64::parse(tokens).field("radius")?;
+        let num_docking_spots = usize::parse(tokens).field("num_docking_spots")?;
+        let current_production = i32::parse(tokens).field("current_production")?;
+        let remaining_resources = i32::parse(tokens).field("remaining_resources")?;
+        let owner = Option::parse(tokens).field("owner")?;
+        let docked_ships = Box::parse(tokens).field("docked_ships")?;
 
-        let id = i32::parse(tokens);
-        let position = Position::parse(tokens);
-        let hp = i32::parse(tokens);
-        let radius = f64::parse(tokens);
-        let num_docking_spots = i32::parse(tokens) as usize;
-        let current_production = i32::parse(tokens);
-        let remaining_resources = i32::parse(tokens);
-        let owner = Option::parse(tokens);
-        let docked_ships = Box::parse(tokens);
-
-        Self {
+        Ok(Self {
             id,
             position,
             hp,
@@ -61,7 +74,21 @@ impl Decodable for Planet {
             remaining_resources,
             owner,
             docked_ships,
-        }
+        })
+    }
+}
+
+impl Encodable for Planet {
+    fn encode(&self, tokens: &mut Vec<String>) {
+        self.id.encode(tokens);
+        self.position.encode(tokens);
+        self.hp.encode(tokens);
+        self.radius.encode(tokens);
+        self.num_docking_spots.encode(tokens);
+        self.current_production.encode(tokens);
+        self.remaining_resources.encode(tokens);
+        self.owner.encode(tokens);
+        self.docked_ships.encode(tokens);
     }
 }
 
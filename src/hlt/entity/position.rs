@@ -1,6 +1,6 @@
 use std::fmt::{Display, Formatter, Result};
-use std::cmp::Eq;
-use hlt::parse::Decodable;
+use hlt::parse::{Decodable, ParseContext, ParseResult, Tokens};
+use hlt::encode::Encodable;
 use hlt::entity::Entity;
 
 /// A simple wrapper for a coordinate.
@@ -18,14 +18,21 @@ impl Position {
 }
 
 impl Decodable for Position {
-    fn parse<'a, I>(tokens: &mut I) -> Position
+    fn parse<'a, I>(tokens: &mut Tokens<I>) -> ParseResult<Position>
     where
         I: Iterator<Item = &'a str>,
     {
-        let x = f64::parse(tokens);
-        let y = f64::parse(tokens);
+        let x = f64::parse(tokens).field("x")?;
+        let y = f64::parse(tokens).field("y")?;
 
-        Position(x, y)
+        Ok(Position(x, y))
+    }
+}
+
+impl Encodable for Position {
+    fn encode(&self, tokens: &mut Vec<String>) {
+        self.0.encode(tokens);
+        self.1.encode(tokens);
     }
 }
 
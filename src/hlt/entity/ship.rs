@@ -1,15 +1,14 @@
 use std::f64;
-use std::cmp::min;
 use std::fmt::{Display, Formatter, Result};
-use hlt::constants::{DOCK_RADIUS, SHIP_RADIUS, MAX_SPEED};
+use hlt::constants::{DOCK_RADIUS, SHIP_RADIUS};
 use hlt::command::Command;
-use hlt::parse::Decodable;
-use hlt::game_map::GameMap;
+use hlt::parse::{Decodable, ParseContext, ParseResult, Tokens};
+use hlt::encode::Encodable;
 use super::{Position, Planet, DockingStatus};
 use super::Entity;
 
 /// A ship in the game.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Ship {
     pub id: i32,
     pub position: Position,
@@ -75,84 +74,28 @@ impl Ship {
 
         Position(x, y)
     }
-
-    // navigate in the direction or to the target location making slight
-    // adjustments to avoid collisions.
-    pub fn navigate_to<T: Entity>(&self, target: &T, game_map: &GameMap) -> Option<Command> {
-        debug!(
-            "Ship {} navigating from {} to {}",
-            self.id,
-            self.position(),
-            target.position()
-        );
-        let mut attempts = 4 * 50; // should a be a multiple of 4
-        let mut adjust = 0.0;
-        let angular_step = 0.5;
-        let angle = self.angle_with(target).to_radians();
-        let speed = {
-            if self.distance_with(target) < MAX_SPEED as f64 {
-                self.distance_with(target)
-            } else {
-                MAX_SPEED as f64
-            }
-        };
-        while attempts > 0 {
-            let Position(ship_x, ship_y) = self.position();
-            let x = ship_x + speed * f64::cos(angle + adjust);
-            let y = ship_y + speed * f64::sin(angle + adjust);
-            let sub_target = Position(x, y);
-            let pos = self.try_path(&sub_target, game_map);
-            if let Some(pos) = pos {
-                let angle = self.angle_with(&pos);
-                let distance = self.distance_with(&pos);
-                return Some(self.thrust(distance as i32, angle as i32));
-            }
-
-            adjust = match attempts % 4 {
-                0 => adjust + angular_step,
-                2 => adjust - angular_step,
-                _ => adjust * -1.0,
-            };
-            attempts -= 1;
-        }
-        None
-    }
-
-    fn try_path<T: Entity>(&self, target: &T, game_map: &GameMap) -> Option<Position> {
-        trace!("ship {} attempting {}", self.id, target.position());
-        if let Some(_) = game_map.planet_between(self, target, self.radius() + 0.1) {
-            trace!("Planet collision found");
-            return None;
-        }
-        if let Some(ship) = game_map.ship_collision(self, target, 0.1) {
-            trace!("collision with {} detected", ship.id);
-            return None;
-        }
-        trace!("ship {} found sub target {}", self.id, target.position());
-        return Some(target.position());
-    }
 }
 
 impl Decodable for Ship {
-    fn parse<'a, I>(tokens: &mut I) -> Ship
+    fn parse<'a, I>(tokens: &mut Tokens<I>) -> ParseResult<Ship>
     where
         I: Iterator<Item = &'a str>,
     {
-        let id = i32::parse(tokens);
-        let position = Position::parse(tokens);
-        let hp = i32::parse(tokens);
-        let velocity_x = f64::parse(tokens);
-        let velocity_y = f64::parse(tokens);
-        let docking_status = DockingStatus::parse(tokens);
-        let docked_planet_raw = i32::parse(tokens);
+        let id = i32::parse(tokens).field("id")?;
+        let position = Position::parse(tokens).field("position")?;
+        let hp = i32::parse(tokens).field("hp")?;
+        let velocity_x = f64::parse(tokens).field("velocity_x")?;
+        let velocity_y = f64::parse(tokens).field("velocity_y")?;
+        let docking_status = DockingStatus::parse(tokens).field("docking_status")?;
+        let docked_planet_raw = i32::parse(tokens).field("docked_planet")?;
         let docked_planet = match docking_status {
             DockingStatus::UNDOCKED => None,
             _ => Some(docked_planet_raw),
         };
-        let progress = i32::parse(tokens);
-        let cooldown = i32::parse(tokens);
+        let progress = i32::parse(tokens).field("progress")?;
+        let cooldown = i32::parse(tokens).field("cooldown")?;
 
-        Ship {
+        Ok(Ship {
             id,
             position,
             hp,
@@ -162,7 +105,21 @@ impl Decodable for Ship {
             docked_planet,
             progress,
             cooldown,
-        }
+        })
+    }
+}
+
+impl Encodable for Ship {
+    fn encode(&self, tokens: &mut Vec<String>) {
+        self.id.encode(tokens);
+        self.position.encode(tokens);
+        self.hp.encode(tokens);
+        self.velocity_x.encode(tokens);
+        self.velocity_y.encode(tokens);
+        self.docking_status.encode(tokens);
+        self.docked_planet.unwrap_or(0).encode(tokens);
+        self.progress.encode(tokens);
+        self.cooldown.encode(tokens);
     }
 }
 
@@ -0,0 +1,87 @@
+//! Hand built frames for tests. They reach the bot through the wire format
+//! and the usual parse path, just like the engine's.
+
+use std::io::Cursor;
+use super::constants::MAX_SHIP_HEALTH;
+use super::encode::Encodable;
+use super::entity::{DockingStatus, GameState, Planet, Position, Ship};
+use super::game::Game;
+use super::game_map::GameMap;
+use super::player::Player;
+
+/// An undocked ship at full health, at rest and ready to fire.
+pub fn ship(id: i32, x: f64, y: f64) -> Ship {
+    Ship {
+        id,
+        position: Position(x, y),
+        hp: MAX_SHIP_HEALTH,
+        velocity_x: 0.0,
+        velocity_y: 0.0,
+        docking_status: DockingStatus::UNDOCKED,
+        docked_planet: None,
+        progress: 0,
+        cooldown: 0,
+    }
+}
+
+/// `ship` docked at `planet`.
+pub fn docked(ship: Ship, planet: i32) -> Ship {
+    Ship {
+        docking_status: DockingStatus::DOCKED,
+        docked_planet: Some(planet),
+        ..ship
+    }
+}
+
+/// An unowned planet with 3 docking spots and 1000 hp and resources.
+pub fn planet(id: i32, x: f64, y: f64, radius: f64) -> Planet {
+    Planet {
+        id,
+        position: Position(x, y),
+        hp: 1000,
+        radius,
+        num_docking_spots: 3,
+        current_production: 0,
+        remaining_resources: 1000,
+        owner: None,
+        docked_ships: Box::new([]),
+    }
+}
+
+/// `planet` owned by `owner` with the `docked` ships.
+pub fn owned(planet: Planet, owner: i32, docked: &[i32]) -> Planet {
+    Planet {
+        owner: Some(owner),
+        docked_ships: docked.to_vec().into_boxed_slice(),
+        ..planet
+    }
+}
+
+/// A frame with the ships of players 0, 1, ... in turn.
+pub fn state(players: Vec<Vec<Ship>>, planets: Vec<Planet>) -> GameState {
+    GameState {
+        players: players
+            .into_iter()
+            .enumerate()
+            .map(|(id, ships)| Player::new(id as i32, ships.into_boxed_slice()))
+            .collect::<Vec<_>>()
+            .into_boxed_slice(),
+        planets: planets.into_boxed_slice(),
+    }
+}
+
+/// A game on a `width` by `height` map in which we are player 0 and the
+/// engine sends `frames`.
+pub fn game(width: i32, height: i32, frames: &[GameState]) -> Game<Cursor<String>, Vec<u8>> {
+    let mut input = format!("0\n{} {}\n", width, height);
+    for frame in frames {
+        input.push_str(&frame.to_line());
+        input.push('\n');
+    }
+    Game::with_transport(Cursor::new(input), Vec::new()).unwrap()
+}
+
+/// The map of a single frame on a 100 by 100 map.
+pub fn game_map(state: GameState) -> GameMap {
+    game(100, 100, &[state]).update_map().unwrap()
+}
@@ -1,71 +1,159 @@
-use std::io::{stdin, stdout, Write};
-use super::parse::Decodable;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, stdin, stdout, BufRead, StdinLock, Stdout, Write};
+use super::parse::{Decodable, ParseError, Tokens};
 use super::entity::GameState;
-use super::command::Command;
+use super::command::{encode_commands, Command};
+use super::delta::TurnDelta;
 use super::game_map::GameMap;
 
+/// Failure to retrieve a frame from the game engine.
 #[derive(Debug)]
-pub struct Game {
+pub enum GameError {
+    /// The transport failed or was closed by the engine.
+    Io(io::Error),
+    /// A frame was received but could not be decoded.
+    Parse(ParseError),
+}
+
+impl Display for GameError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            GameError::Io(ref err) => write!(f, "transport error: {}", err),
+            GameError::Parse(ref err) => write!(f, "parse error: {}", err),
+        }
+    }
+}
+
+impl Error for GameError {}
+
+impl From<io::Error> for GameError {
+    fn from(err: io::Error) -> Self {
+        GameError::Io(err)
+    }
+}
+
+impl From<ParseError> for GameError {
+    fn from(err: ParseError) -> Self {
+        GameError::Parse(err)
+    }
+}
+
+/// Connection to the game engine. Frames are read from `R` and commands
+/// written to `W`, which default to stdin and stdout.
+#[derive(Debug)]
+pub struct Game<R = StdinLock<'static>, W = Stdout> {
     pub my_id: usize,
     pub map_width: i32,
     pub map_height: i32,
+    /// The last frame received, to tell what changed in the next one.
+    previous: Option<GameState>,
+    reader: R,
+    writer: W,
 }
 
 impl Game {
-    fn read_line() -> String {
-        let mut buffer = String::new();
-        stdin().read_line(&mut buffer).expect("Read error");
-        buffer
+    /// Perform the initial handshake with the engine over stdin and stdout.
+    pub fn new() -> Game {
+        Game::with_transport(stdin().lock(), stdout())
+            .unwrap_or_else(|err| panic!("Unable to start game: {}", err))
     }
+}
+
+impl<R: BufRead, W: Write> Game<R, W> {
+    /// Perform the initial handshake with the engine over the given
+    /// transport, reading our player id and the map size.
+    pub fn with_transport(reader: R, writer: W) -> Result<Self, GameError> {
+        let mut game = Game {
+            my_id: 0,
+            map_width: 0,
+            map_height: 0,
+            previous: None,
+            reader,
+            writer,
+        };
+
+        game.my_id = game.read_id()?;
+        let (map_width, map_height) = game.read_size()?;
+        game.map_width = map_width;
+        game.map_height = map_height;
 
-    fn read_id() -> usize {
-        let line = Game::read_line();
-        let parts = line.split_whitespace();
-        let mut iter = parts.into_iter();
-        usize::parse(&mut iter)
+        Ok(game)
     }
 
-    fn read_size() -> (i32, i32) {
-        let line = Game::read_line();
-        let parts = line.split_whitespace();
-        let mut iter = parts.into_iter();
-        let width = i32::parse(&mut iter);
-        let height = i32::parse(&mut iter);
-        (width, height)
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut buffer = String::new();
+        if self.reader.read_line(&mut buffer)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Game engine closed the input stream",
+            ));
+        }
+        Ok(buffer)
     }
 
-    pub fn new() -> Game {
-        let my_id = Game::read_id();
-        let (map_width, map_height) = Game::read_size();
+    fn read_id(&mut self) -> Result<usize, GameError> {
+        let line = self.read_line()?;
+        Ok(usize::parse(&mut Tokens::from_line(&line))?)
+    }
 
-        Game {
-            my_id,
-            map_width,
-            map_height,
-        }
+    fn read_size(&mut self) -> Result<(i32, i32), GameError> {
+        let line = self.read_line()?;
+        let mut tokens = Tokens::from_line(&line);
+        let width = i32::parse(&mut tokens)?;
+        let height = i32::parse(&mut tokens)?;
+        Ok((width, height))
     }
 
     /// Send your bot name, terminating the preprocessing
     /// time of 60 seconds allowed at the start of a game
-    pub fn send_ready(&self, name: &str) {
-        println!("{}", name)
+    pub fn send_ready(&mut self, name: &str) -> io::Result<()> {
+        writeln!(self.writer, "{}", name)?;
+        self.writer.flush()
     }
 
-    /// Retrieve the new updated map
-    pub fn update_map(&self) -> GameMap {
-        let line = Game::read_line();
-        let parts = line.split_whitespace();
-        let mut iter = parts.into_iter();
-        let game_state = GameState::parse(&mut iter);
-        GameMap::new(self, game_state)
+    /// Retrieve the new updated map. A malformed frame is reported as
+    /// `GameError::Parse` so the caller can skip the turn instead of crashing,
+    /// and the next map is compared against the last frame that parsed.
+    pub fn update_map(&mut self) -> Result<GameMap, GameError> {
+        let line = self.read_line()?;
+        let game_state = GameState::parse(&mut Tokens::from_line(&line))?;
+        let delta = TurnDelta::between(self.previous.as_ref(), &game_state);
+        self.previous = Some(game_state.clone());
+        Ok(GameMap::new(self, game_state, delta))
     }
 
     /// Send all commands to the game
-    pub fn send_command_queue(&self, commands: &[Command]) {
-        for command in commands {
-            let encoded = command.encode();
-            stdout().write(encoded.as_bytes()).unwrap();
+    pub fn send_command_queue(&mut self, commands: &[Command]) -> io::Result<()> {
+        writeln!(self.writer, "{}", encode_commands(commands))?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use hlt::command::Command;
+    use super::{Game, GameError};
+
+    #[test]
+    fn test_game_over_transport() {
+        let input = "1\n240 160\n2 0 0 1 1 3 1.0 2.0 255 0.0 0.0 0 0 0 0 0\n";
+        let mut output = Vec::new();
+        {
+            let mut game = Game::with_transport(Cursor::new(input), &mut output).unwrap();
+            assert_eq!(1, game.my_id);
+            assert_eq!((240, 160), (game.map_width, game.map_height));
+
+            game.send_ready("rusty").unwrap();
+            assert_eq!(3, game.update_map().unwrap().me().all_ships()[0].id);
+            game.send_command_queue(&[Command::Thrust(3, 7, 90), Command::Nop, Command::Undock(4)])
+                .unwrap();
+            match game.update_map() {
+                Err(GameError::Io(_)) => (),
+                other => panic!("Expected end of input, got {:?}", other),
+            }
         }
-        println!()
+        assert_eq!("rusty\nt 3 7 90 u 4\n", String::from_utf8(output).unwrap());
     }
 }
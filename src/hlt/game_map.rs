@@ -1,31 +1,89 @@
 use std::collections::HashMap;
 use std::cmp::Ordering::{Less, Equal, Greater};
+use std::io::{BufRead, Write};
+use super::delta::TurnDelta;
 use super::game::Game;
-use super::entity::{GameState, Planet};
+use super::entity::{GameState, Planet, Position};
 use super::constants::MAX_SPEED;
 use super::player::Player;
 use super::collision::intersect_segment_circle;
 use super::entity::{Entity, Ship};
+use super::spatial::SpatialGrid;
+
+/// Side length of a cell in the spatial index.
+const GRID_CELL_SIZE: f64 = 8.0;
 
 /// Map which houses the current game information/metadata.
 #[derive(Debug)]
-pub struct GameMap<'a> {
-    game: &'a Game,
+pub struct GameMap {
+    my_id: usize,
+    width: i32,
+    height: i32,
     state: GameState,
+    delta: TurnDelta,
+    /// Every ship as (player index, ship index), indexed by `ship_grid`.
+    ships: Vec<(usize, usize)>,
+    /// Position in `ships` by ship id.
+    ship_index: HashMap<i32, usize>,
+    /// Position in the planet list by planet id.
+    planet_index: HashMap<i32, usize>,
+    ship_grid: SpatialGrid,
+    planet_grid: SpatialGrid,
 }
 
-impl<'a> GameMap<'a> {
-    pub fn new(game: &'a Game, state: GameState) -> Self {
+impl GameMap {
+    pub fn new<R: BufRead, W: Write>(game: &Game<R, W>, state: GameState, delta: TurnDelta) -> Self {
+        let mut ships = Vec::new();
+        let mut ship_index = HashMap::new();
+        let mut ship_grid = SpatialGrid::new(GRID_CELL_SIZE);
+        for (p, player) in state.players.iter().enumerate() {
+            for (s, ship) in player.all_ships().iter().enumerate() {
+                ship_index.insert(ship.id, ships.len());
+                ship_grid.insert(ships.len(), ship);
+                ships.push((p, s));
+            }
+        }
+
+        let mut planet_index = HashMap::new();
+        let mut planet_grid = SpatialGrid::new(GRID_CELL_SIZE);
+        for (i, planet) in state.planets.iter().enumerate() {
+            planet_index.insert(planet.id, i);
+            planet_grid.insert(i, planet);
+        }
+
         Self {
-            game: game,
-            state: state,
+            my_id: game.my_id,
+            width: game.map_width,
+            height: game.map_height,
+            state,
+            delta,
+            ships,
+            ship_index,
+            planet_index,
+            ship_grid,
+            planet_grid,
         }
     }
 
     /// Return your own player.
     pub fn me(&self) -> &Player {
-        let my_id = self.game.my_id;
-        &self.state.players[my_id]
+        &self.state.players[self.my_id]
+    }
+
+    /// What changed since the previous turn.
+    pub fn delta(&self) -> &TurnDelta {
+        &self.delta
+    }
+
+    /// Width and height of the map, ships leaving it are destroyed.
+    pub fn size(&self) -> (i32, i32) {
+        (self.width, self.height)
+    }
+
+    /// Test whether a point lies on the map.
+    pub fn contains<T: Entity>(&self, pos: &T) -> bool {
+        let Position(x, y) = pos.position();
+        x >= 0.0 && y >= 0.0 && x <= self.width as f64 && y <= self.height as f64
     }
 
     /// Returns all planets at the actual game state.
@@ -38,57 +96,147 @@ impl<'a> GameMap<'a> {
         &self.state.players
     }
 
-    pub fn all_ships(&self) -> Box<[&Ship]> {
-        let mut ships = Vec::new();
-        for player in self.all_players() {
-            for ship in player.all_ships() {
-                ships.push(ship)
-            }
-        }
-        ships.into_boxed_slice()
+    /// Returns the ships of every player including yourself.
+    pub fn all_ships(&self) -> impl Iterator<Item = &Ship> {
+        self.ships.iter().map(move |&(p, s)| &self.state.players[p].all_ships()[s])
     }
 
     pub fn get_planet(&self, planet_id: i32) -> Option<&Planet> {
-        for planet in self.all_planets() {
-            if planet.id == planet_id {
-                if planet.hp <= 0 {
-                    return None;
-                } else {
-                    return Some(planet);
-                }
-            }
-        }
-        None
+        self.planet_index
+            .get(&planet_id)
+            .map(|&i| &self.state.planets[i])
+            .filter(|planet| planet.hp > 0)
     }
 
     pub fn get_ship(&self, ship_id: i32) -> Option<&Ship> {
-        for player in self.all_players() {
-            for ship in player.all_ships() {
-                if ship.id == ship_id {
-                    return Some(ship);
-                }
+        self.ship_index.get(&ship_id).map(|&i| self.ship_at(i))
+    }
+
+    /// Returns a ship together with the player it belongs to.
+    pub fn get_ship_with_owner(&self, ship_id: i32) -> Option<(&Player, &Ship)> {
+        self.ship_index
+            .get(&ship_id)
+            .map(|&i| (&self.state.players[self.owner_index(i)], self.ship_at(i)))
+    }
+
+    /// Returns the player a ship belongs to.
+    pub fn owner_of_ship(&self, ship_id: i32) -> Option<&Player> {
+        self.get_ship_with_owner(ship_id).map(|(player, _)| player)
+    }
+
+    /// Returns the ships docked or docking at a planet.
+    pub fn ships_docked_at(&self, planet_id: i32) -> Vec<&Ship> {
+        self.get_planet(planet_id)
+            .map(|planet| {
+                planet
+                    .docked_ships
+                    .iter()
+                    .filter_map(|&id| self.get_ship(id))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Index of the player owning the ship at `index` of the flat ship list.
+    fn owner_index(&self, index: usize) -> usize {
+        self.ships[index].0
+    }
+
+    fn ship_at(&self, index: usize) -> &Ship {
+        let (p, s) = self.ships[index];
+        &self.state.players[p].all_ships()[s]
+    }
+
+    /// Returns every ship whose center lies within `radius` of `pos`.
+    pub fn ships_within<T: Entity>(&self, pos: &T, radius: f64) -> Vec<&Ship> {
+        self.ship_indices_within(pos, radius)
+            .into_iter()
+            .map(|i| self.ship_at(i))
+            .collect()
+    }
+
+    fn ship_indices_within<T: Entity>(&self, pos: &T, radius: f64) -> Vec<usize> {
+        self.ship_grid
+            .query_circle(&pos.position(), radius)
+            .into_iter()
+            .filter(|&i| self.ship_at(i).distance_with(pos) <= radius)
+            .collect()
+    }
+
+    /// Returns every planet whose surface lies within `radius` of `pos`.
+    pub fn planets_within<T: Entity>(&self, pos: &T, radius: f64) -> Vec<&Planet> {
+        self.planet_grid
+            .query_circle(&pos.position(), radius)
+            .into_iter()
+            .map(|i| &self.state.planets[i])
+            .filter(|planet| planet.distance_with(pos) - planet.radius <= radius)
+            .collect()
+    }
+
+    /// Returns the closest ship that does not belong to us, `None` for a
+    /// position that isn't finite.
+    pub fn nearest_enemy_ship<T: Entity>(&self, pos: &T) -> Option<&Ship> {
+        let furthest = self.ship_grid.furthest_distance(&pos.position());
+        if self.ship_grid.is_empty() || !furthest.is_finite() {
+            return None;
+        }
+        let mut radius = GRID_CELL_SIZE;
+        loop {
+            // Anything found within the radius is closer than what lies
+            // outside of it, so the closest candidate is the answer.
+            let nearest = self.ship_indices_within(pos, radius)
+                .into_iter()
+                .filter(|&i| self.owner_index(i) != self.my_id)
+                .map(|i| self.ship_at(i))
+                .min_by(|a, b| {
+                    a.distance_with(pos)
+                        .partial_cmp(&b.distance_with(pos))
+                        .unwrap_or(Equal)
+                });
+            if nearest.is_some() || radius >= furthest {
+                return nearest;
             }
+            radius *= 2.0;
         }
-        None
+    }
+
+    /// Returns the ships intersecting the segment from `start` to `end`,
+    /// with their radius grown by `fudge`.
+    pub fn ships_along<E: Entity, T: Entity>(&self, start: &E, end: &T, fudge: f64) -> Vec<&Ship> {
+        self.ship_grid
+            .query_segment(&start.position(), &end.position(), fudge)
+            .into_iter()
+            .map(|i| self.ship_at(i))
+            .filter(|ship| intersect_segment_circle(start, end, *ship, fudge))
+            .collect()
+    }
+
+    /// Returns the planets intersecting the segment from `start` to `end`,
+    /// with their radius grown by `fudge`.
+    pub fn planets_along<E: Entity, T: Entity>(&self, start: &E, end: &T, fudge: f64) -> Vec<&Planet> {
+        self.planet_grid
+            .query_segment(&start.position(), &end.position(), fudge)
+            .into_iter()
+            .map(|i| &self.state.planets[i])
+            .filter(|planet| intersect_segment_circle(start, end, *planet, fudge))
+            .collect()
     }
 
     pub fn obstacles_between<T: Entity>(&self, ship: &Ship, target: &T, fudge: f64) -> bool {
-        for planet in self.all_planets() {
-            if intersect_segment_circle(ship, target, planet, ship.radius() + fudge) {
-                return true;
-            }
+        if !self.planets_along(ship, target, ship.radius() + fudge).is_empty() {
+            return true;
         }
 
-        for entity in self.all_ships().iter() {
-            if ship != *entity &&
+        for entity in self.ships_within(target, MAX_SPEED as f64) {
+            if ship != entity &&
                 entity.distance_with(target) < MAX_SPEED as f64 &&
-                intersect_segment_circle(ship, target, *entity, 0.1)
+                intersect_segment_circle(ship, target, entity, 0.1)
             {
                 trace!("Collision detected between {} and {}", ship.id, entity.id);
                 return true;
             }
         }
-        return false;
+        false
     }
 
     pub fn planet_between<E: Entity, T: Entity>(
@@ -97,11 +245,8 @@ impl<'a> GameMap<'a> {
         target: &T,
         fudge: f64,
     ) -> Option<&Planet> {
-        self.all_planets()
-            .iter()
-            .filter(|planet| {
-                intersect_segment_circle(ship, target, *planet, ship.radius() + fudge)
-            })
+        self.planets_along(ship, target, ship.radius() + fudge)
+            .into_iter()
             .min_by(|&a, &b| {
                 let dist_a = ship.distance_with(a);
                 let dist_b = ship.distance_with(b);
@@ -114,17 +259,54 @@ impl<'a> GameMap<'a> {
                 }
             })
     }
+}
 
-    pub fn ship_collision<T: Entity>(&self, ship: &Ship, target: &T, fudge: f64) -> Option<&Ship> {
-        for ship_ in self.me().all_ships() {
-            let distance = target.distance_with(ship_);
-            if distance >= MAX_SPEED as f64 || ship.id == ship_.id {
-                continue;
-            }
-            if intersect_segment_circle(ship, target, ship_, ship_.radius() + fudge) {
-                return Some(ship_);
-            }
-        }
-        None
+#[cfg(test)]
+mod tests {
+    use hlt::entity::{DockingStatus, Position, Ship};
+    use hlt::fixture::{docked, game_map, owned, planet, ship, state};
+
+    #[test]
+    fn test_ships_docked_at() {
+        // Ships 0 and 1 of player 0 are docked at planet 4, ship 2 is not.
+        let docking = Ship {
+            docking_status: DockingStatus::DOCKING,
+            progress: 3,
+            ..docked(ship(1, 12.0, 10.0), 4)
+        };
+        let game_map = game_map(state(
+            vec![vec![docked(ship(0, 10.0, 10.0), 4), docking, ship(2, 50.0, 50.0)]],
+            vec![owned(planet(4, 10.0, 20.0, 5.0), 0, &[0, 1])],
+        ));
+
+        let docked: Vec<i32> = game_map.ships_docked_at(4).iter().map(|ship| ship.id).collect();
+        assert_eq!(vec![0, 1], docked);
+        assert!(game_map.ships_docked_at(5).is_empty());
+        assert_eq!(0, game_map.get_ship_with_owner(1).unwrap().0.id);
+    }
+
+    #[test]
+    fn test_spatial_queries() {
+        // We own ship 0 at (10, 10), the enemy owns ships 1 at (60, 10) and
+        // 2 at (90, 90). A planet of radius 5 sits at (30, 10).
+        let game_map = game_map(state(
+            vec![vec![ship(0, 10.0, 10.0)], vec![ship(1, 60.0, 10.0), ship(2, 90.0, 90.0)]],
+            vec![planet(0, 30.0, 10.0, 5.0)],
+        ));
+        let origin = Position(10.0, 10.0);
+
+        assert_eq!(3, game_map.all_ships().count());
+        assert_eq!(1, game_map.ships_within(&origin, 1.0).len());
+        assert_eq!(90.0, game_map.get_ship(2).unwrap().position.0);
+        assert_eq!(1, game_map.owner_of_ship(2).unwrap().id);
+        assert!(game_map.get_ship(3).is_none());
+        assert_eq!(30.0, game_map.get_planet(0).unwrap().position.0);
+        assert_eq!(1, game_map.planets_within(&origin, 15.0).len());
+        assert!(game_map.planets_within(&origin, 14.0).is_empty());
+        assert_eq!(1, game_map.nearest_enemy_ship(&origin).unwrap().id);
+        assert_eq!(2, game_map.nearest_enemy_ship(&Position(80.0, 80.0)).unwrap().id);
+        assert!(game_map.nearest_enemy_ship(&Position(f64::NAN, 10.0)).is_none());
+        assert_eq!(0, game_map.planet_between(&origin, &Position(60.0, 10.0), 0.0).unwrap().id);
+        assert_eq!(2, game_map.ships_along(&origin, &Position(70.0, 10.0), 0.0).len());
     }
 }
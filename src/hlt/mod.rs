@@ -5,4 +5,11 @@ pub mod constants;
 pub mod game_map;
 pub mod player;
 pub mod collision;
+pub mod simulator;
+pub mod spatial;
+pub mod navigation;
+pub mod delta;
+#[cfg(test)]
+pub mod fixture;
 mod parse;
+mod encode;
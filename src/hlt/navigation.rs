@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::f64;
+use super::collision::{intersect_moving_circles, line_of_sight, Obstacle};
+use super::command::Command;
+use super::constants::{MAX_SPEED, SHIP_RADIUS};
+use super::entity::{DockingStatus, Entity, Position, Ship};
+use super::game_map::GameMap;
+
+/// Degrees the heading is turned by between two navigation attempts.
+const ANGULAR_STEP: f64 = 3.0;
+
+/// Extra room kept between two ships on their way.
+const SHIP_FUDGE: f64 = 0.1;
+
+/// Where a ship starts the turn and how far it moves during it.
+#[derive(Debug, Clone, Copy)]
+pub struct Trajectory {
+    pub start: Position,
+    pub velocity: (f64, f64),
+}
+
+impl Trajectory {
+    /// The trajectory of `ship` if it were given `command`.
+    pub fn of(ship: &Ship, command: &Command) -> Self {
+        let velocity = match *command {
+            Command::Thrust(_, magnitude, angle) => {
+                let angle = (angle as f64).to_radians();
+                (magnitude as f64 * angle.cos(), magnitude as f64 * angle.sin())
+            }
+            _ => (0.0, 0.0),
+        };
+        Trajectory {
+            start: ship.position(),
+            velocity,
+        }
+    }
+
+    pub fn end(&self) -> Position {
+        let Position(x, y) = self.start;
+        Position(x + self.velocity.0, y + self.velocity.1)
+    }
+}
+
+/// The moves planned for our ships during the current turn. Ships are
+/// planned one after another, and each new path must stay clear of the ships
+/// planned before it. Ships without a plan yet are assumed to stay put.
+#[derive(Debug, Default)]
+pub struct NavigationPlan {
+    trajectories: HashMap<i32, Trajectory>,
+}
+
+impl NavigationPlan {
+    pub fn new() -> Self {
+        NavigationPlan { trajectories: HashMap::new() }
+    }
+
+    pub fn get(&self, ship_id: i32) -> Option<&Trajectory> {
+        self.trajectories.get(&ship_id)
+    }
+
+    /// Record the command a ship was given this turn.
+    pub fn reserve(&mut self, ship: &Ship, command: &Command) {
+        self.trajectories.insert(ship.id, Trajectory::of(ship, command));
+    }
+
+    /// Reserve `command` if it keeps `ship` clear of planets and of the moves
+    /// planned so far, returning whether it did.
+    pub fn try_reserve(&mut self, ship: &Ship, command: &Command, game_map: &GameMap) -> bool {
+        if !self.is_clear(ship, &Trajectory::of(ship, command), game_map) {
+            return false;
+        }
+        self.reserve(ship, command);
+        true
+    }
+
+    /// The trajectory of one of our ships, planned or not.
+    fn trajectory_of(&self, ship: &Ship) -> Trajectory {
+        self.get(ship.id)
+            .cloned()
+            .unwrap_or_else(|| Trajectory::of(ship, &Command::Nop))
+    }
+
+    /// Returns the id of one of our ships `ship` would run into by following
+    /// `trajectory`, if any.
+    pub fn conflict(&self, ship: &Ship, trajectory: &Trajectory, game_map: &GameMap) -> Option<i32> {
+        let me = game_map.me();
+        let reach = 2.0 * (MAX_SPEED as f64 + SHIP_RADIUS) + SHIP_FUDGE;
+        game_map
+            .ships_within(ship, reach)
+            .into_iter()
+            .filter(|other| other.id != ship.id && me.get_ship(other.id).is_some())
+            .find(|other| {
+                let theirs = self.trajectory_of(other);
+                intersect_moving_circles(
+                    trajectory.start,
+                    trajectory.velocity,
+                    theirs.start,
+                    theirs.velocity,
+                    ship.radius() + other.radius() + SHIP_FUDGE,
+                )
+            })
+            .map(|other| other.id)
+    }
+
+    /// Navigate towards `target`, avoiding planets, docked enemy ships and the
+    /// trajectories of our other ships. Conflicts are resolved by turning up to `max_corrections`
+    /// times, first at full then at half speed, and finally by waiting in
+    /// place. The chosen move is reserved in the plan; waiting returns `None`.
+    pub fn navigate<T: Entity>(
+        &mut self,
+        ship: &Ship,
+        target: &T,
+        game_map: &GameMap,
+        max_corrections: i32,
+    ) -> Option<Command> {
+        debug!(
+            "Ship {} planning from {} to {}",
+            ship.id,
+            ship.position(),
+            target.position()
+        );
+        let heading = ship.angle_with(target);
+        let full = ship.distance_with(target).min(MAX_SPEED as f64);
+
+        for &speed in &[full, full / 2.0] {
+            let magnitude = speed as i32;
+            if magnitude == 0 {
+                continue;
+            }
+            for attempt in 0..(max_corrections / 2).max(1) {
+                // Alternate sides: 0, +1, -1, +2, -2, ... steps.
+                let steps = ((attempt + 1) / 2) as f64;
+                let offset = if attempt % 2 == 0 { -steps } else { steps } * ANGULAR_STEP;
+                if offset.abs() > 180.0 {
+                    break;
+                }
+                let angle = ((heading + offset).round() as i32).rem_euclid(360);
+                let command = ship.thrust(magnitude, angle);
+                if self.is_clear(ship, &Trajectory::of(ship, &command), game_map) {
+                    self.reserve(ship, &command);
+                    return Some(command);
+                }
+            }
+        }
+
+        debug!("Ship {}: no clear path, waiting", ship.id);
+        self.reserve(ship, &Command::Nop);
+        None
+    }
+
+    fn is_clear(&self, ship: &Ship, trajectory: &Trajectory, game_map: &GameMap) -> bool {
+        let end = trajectory.end();
+        if let Some(planet) = game_map.planet_between(ship, &end, ship.radius() + SHIP_FUDGE) {
+            trace!("Ship {}: planet {} in the way", ship.id, planet.id);
+            return false;
+        }
+        if let Some(other) = self.conflict(ship, trajectory, game_map) {
+            trace!("Ship {}: would collide with ship {}", ship.id, other);
+            return false;
+        }
+        // Docked enemies stay put, the ones in flight and ours are left to
+        // the plan above.
+        let me = game_map.me();
+        let moving: Vec<Obstacle> = game_map
+            .ships_within(ship, MAX_SPEED as f64 + 2.0 * SHIP_RADIUS + SHIP_FUDGE)
+            .into_iter()
+            .filter(|other| {
+                me.get_ship(other.id).is_some() || other.docking_status == DockingStatus::UNDOCKED
+            })
+            .map(|other| Obstacle::Ship(other.id))
+            .collect();
+        if !line_of_sight(game_map, ship, &end, ship.radius() + SHIP_FUDGE, &moving) {
+            trace!("Ship {}: docked ship in the way", ship.id);
+            return false;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hlt::command::Command;
+    use hlt::entity::Position;
+    use hlt::fixture::{docked, game_map, ship, state};
+    use super::{NavigationPlan, Trajectory};
+
+    #[test]
+    fn test_crossing_paths() {
+        // Our ships 0 at (10, 10) and 1 at (15, 5) head to targets whose
+        // straight paths cross at (15, 10) in the same turn.
+        let game_map = game_map(state(vec![vec![ship(0, 10.0, 10.0), ship(1, 15.0, 5.0)]], vec![]));
+        let first = game_map.get_ship(0).unwrap();
+        let second = game_map.get_ship(1).unwrap();
+
+        let mut plan = NavigationPlan::new();
+        let command = plan.navigate(first, &Position(20.0, 10.0), &game_map, 20);
+        assert_eq!(Some(Command::Thrust(0, 7, 0)), command);
+
+        let straight = Trajectory::of(second, &Command::Thrust(1, 7, 90));
+        assert_eq!(Some(0), plan.conflict(second, &straight, &game_map));
+
+        let command = plan.navigate(second, &Position(15.0, 15.0), &game_map, 20);
+        assert!(command.is_some());
+        assert!(command != Some(Command::Thrust(1, 7, 90)));
+        let planned = *plan.get(1).unwrap();
+        assert_eq!(None, plan.conflict(second, &planned, &game_map));
+    }
+
+    #[test]
+    fn test_docked_enemy() {
+        // Our ship 0 at (10, 10) heads east through the docked enemy ship 1
+        // at (14, 10), the undocked enemy ship 2 at (10, 14) is left alone.
+        let game_map = game_map(state(
+            vec![vec![ship(0, 10.0, 10.0)], vec![docked(ship(1, 14.0, 10.0), 0), ship(2, 10.0, 14.0)]],
+            vec![],
+        ));
+        let ship = game_map.get_ship(0).unwrap();
+
+        let mut plan = NavigationPlan::new();
+        assert!(!plan.try_reserve(ship, &Command::Thrust(0, 7, 0), &game_map));
+        assert!(plan.try_reserve(ship, &Command::Thrust(0, 7, 90), &game_map));
+    }
+}
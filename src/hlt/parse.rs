@@ -1,29 +1,168 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
 use std::str::FromStr;
-use std::fmt::Debug;
 
-pub trait Decodable {
-    fn parse<'a, I>(&mut I) -> Self
+/// Result of decoding a value from the engine's token stream.
+pub type ParseResult<T> = Result<T, ParseError>;
+
+/// One step of the path from the root of a frame to the value that failed
+/// to decode, e.g. the `ships` in `players[1].ships[3].hp`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment {
+    Field(&'static str),
+    Index(usize),
+}
+
+/// Describes why a token could not be decoded and where it was found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// Zero based index of the offending token within the line.
+    pub index: usize,
+    /// Name of the type that was expected at `index`.
+    pub expected: &'static str,
+    /// The offending token, or `None` if the input ended early.
+    pub found: Option<String>,
+    /// Location of the value inside the decoded structure, outermost first.
+    pub path: Vec<PathSegment>,
+}
+
+impl ParseError {
+    pub fn new(index: usize, expected: &'static str, found: Option<&str>) -> Self {
+        ParseError {
+            index,
+            expected,
+            found: found.map(|token| token.to_string()),
+            path: Vec::new(),
+        }
+    }
+
+    /// Render the path as a field access expression such as
+    /// `players[1].ships[3].hp`.
+    pub fn path_string(&self) -> String {
+        let mut result = String::new();
+        for segment in &self.path {
+            match *segment {
+                PathSegment::Field(name) => {
+                    if !result.is_empty() {
+                        result.push('.');
+                    }
+                    result.push_str(name);
+                }
+                PathSegment::Index(i) => result.push_str(&format!("[{}]", i)),
+            }
+        }
+        result
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let found = match self.found {
+            Some(ref token) => format!("{:?}", token),
+            None => "end of input".to_string(),
+        };
+        write!(
+            f,
+            "token {}: expected {}, found {}",
+            self.index,
+            self.expected,
+            found
+        )?;
+        if !self.path.is_empty() {
+            write!(f, " at {}", self.path_string())?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ParseError {}
+
+/// Adds location information to a `ParseError` as it bubbles up through
+/// the structure being decoded.
+pub trait ParseContext<T> {
+    /// Prefix the error path with a named field.
+    fn field(self, name: &'static str) -> ParseResult<T>;
+
+    /// Prefix the error path with a collection index.
+    fn index(self, i: usize) -> ParseResult<T>;
+}
+
+impl<T> ParseContext<T> for ParseResult<T> {
+    fn field(self, name: &'static str) -> ParseResult<T> {
+        self.map_err(|mut err| {
+            err.path.insert(0, PathSegment::Field(name));
+            err
+        })
+    }
+
+    fn index(self, i: usize) -> ParseResult<T> {
+        self.map_err(|mut err| {
+            err.path.insert(0, PathSegment::Index(i));
+            err
+        })
+    }
+}
+
+/// Whitespace separated tokens from a single line of engine output, keeping
+/// track of how many have been consumed.
+pub struct Tokens<I> {
+    inner: I,
+    index: usize,
+}
+
+impl<'a, I> Tokens<I>
+where
+    I: Iterator<Item = &'a str>,
+{
+    pub fn new(inner: I) -> Self {
+        Tokens { inner, index: 0 }
+    }
+
+    /// Index of the next token to be read.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Take the next token, failing if the input is exhausted.
+    pub fn next_token(&mut self, expected: &'static str) -> ParseResult<&'a str> {
+        match self.inner.next() {
+            Some(token) => {
+                self.index += 1;
+                Ok(token)
+            }
+            None => Err(ParseError::new(self.index, expected, None)),
+        }
+    }
+}
+
+impl<'a> Tokens<::std::str::SplitWhitespace<'a>> {
+    pub fn from_line(line: &'a str) -> Self {
+        Tokens::new(line.split_whitespace())
+    }
+}
+
+pub trait Decodable: Sized {
+    fn parse<'a, I>(tokens: &mut Tokens<I>) -> ParseResult<Self>
     where
         I: Iterator<Item = &'a str>;
 }
 
-fn parse_next_primitive<'a, T: FromStr, I>(tokens: &mut I, type_name: &str) -> T
+fn parse_next_primitive<'a, T: FromStr, I>(
+    tokens: &mut Tokens<I>,
+    type_name: &'static str,
+) -> ParseResult<T>
 where
     I: Iterator<Item = &'a str>,
-    <T as FromStr>::Err: Debug,
 {
-    let next_token = tokens.next().expect("Expected token, got EOF");
-    next_token.parse::<T>().unwrap_or_else(|_| {
-        panic!(
-            "Could not parse {:?} as expected type {:?}",
-            next_token,
-            type_name
-        )
-    })
+    let index = tokens.index();
+    let next_token = tokens.next_token(type_name)?;
+    next_token
+        .parse::<T>()
+        .map_err(|_| ParseError::new(index, type_name, Some(next_token)))
 }
 
 impl Decodable for f64 {
-    fn parse<'a, I>(tokens: &mut I) -> f64
+    fn parse<'a, I>(tokens: &mut Tokens<I>) -> ParseResult<f64>
     where
         I: Iterator<Item = &'a str>,
     {
@@ -32,7 +171,7 @@ impl Decodable for f64 {
 }
 
 impl Decodable for i32 {
-    fn parse<'a, I>(tokens: &mut I) -> i32
+    fn parse<'a, I>(tokens: &mut Tokens<I>) -> ParseResult<i32>
     where
         I: Iterator<Item = &'a str>,
     {
@@ -41,7 +180,7 @@ impl Decodable for i32 {
 }
 
 impl Decodable for usize {
-    fn parse<'a, I>(tokens: &mut I) -> usize
+    fn parse<'a, I>(tokens: &mut Tokens<I>) -> ParseResult<usize>
     where
         I: Iterator<Item = &'a str>,
     {
@@ -50,45 +189,85 @@ impl Decodable for usize {
 }
 
 impl Decodable for bool {
-    fn parse<'a, I>(tokens: &mut I) -> bool
+    fn parse<'a, I>(tokens: &mut Tokens<I>) -> ParseResult<bool>
     where
         I: Iterator<Item = &'a str>,
     {
-        let p = parse_next_primitive(tokens, "bool");
-        match p {
-            0 => false,
-            1 => true,
-            _ => panic!("Expected 0 or 1, got {:?}", p),
+        let index = tokens.index();
+        let token = tokens.next_token("bool")?;
+        match token {
+            "0" => Ok(false),
+            "1" => Ok(true),
+            _ => Err(ParseError::new(index, "bool", Some(token))),
         }
     }
 }
 
 impl<T: Decodable> Decodable for Box<[T]> {
-    fn parse<'a, I>(tokens: &mut I) -> Box<[T]>
+    fn parse<'a, I>(tokens: &mut Tokens<I>) -> ParseResult<Box<[T]>>
     where
         I: Iterator<Item = &'a str>,
     {
-        let size: i64 = parse_next_primitive(tokens, "i64");
-        let mut result = Vec::with_capacity(size as usize);
-        for _ in 0..size {
-            let p = T::parse(tokens);
-            result.push(p);
+        // The count comes off the wire, so it can't be trusted for an allocation
+        let size: usize = parse_next_primitive(tokens, "usize")?;
+        let mut result = Vec::new();
+        for i in 0..size {
+            result.push(T::parse(tokens).index(i)?);
         }
-        result.into_boxed_slice()
+        Ok(result.into_boxed_slice())
     }
 }
 
 impl<T: Decodable> Decodable for Option<T> {
-    fn parse<'a, I>(tokens: &mut I) -> Option<T>
+    fn parse<'a, I>(tokens: &mut Tokens<I>) -> ParseResult<Option<T>>
     where
         I: Iterator<Item = &'a str>,
     {
-        let present = bool::parse(tokens);
-        let value = T::parse(tokens);
+        let present = bool::parse(tokens)?;
+        let value = T::parse(tokens)?;
 
-        match present {
-            true => Some(value),
-            false => None,
-        }
+        Ok(if present { Some(value) } else { None })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Decodable, PathSegment, Tokens};
+    use hlt::entity::GameState;
+
+    #[test]
+    fn test_parse_game_state() {
+        let line = "1 0 1 3 10.0 20.0 255 0.0 0.0 0 0 0 0 \
+                    1 7 30.0 40.0 1000 5.0 3 0 1000 1 0 1 3";
+        let state = GameState::parse(&mut Tokens::from_line(line)).unwrap();
+        assert_eq!(1, state.players.len());
+        assert_eq!(3, state.players[0].all_ships()[0].id);
+        assert_eq!(1, state.planets.len());
+        assert_eq!(Some(0), state.planets[0].owner);
+        assert_eq!(3, state.planets[0].docked_ships[0]);
+    }
+
+    #[test]
+    fn test_parse_error_location() {
+        let line = "1 0 2 3 10.0 20.0 255 0.0 0.0 0 0 0 0 4 1.0 2.0 oops";
+        let err = GameState::parse(&mut Tokens::from_line(line)).unwrap_err();
+        assert_eq!(16, err.index);
+        assert_eq!("i32", err.expected);
+        assert_eq!(Some("oops".to_string()), err.found);
+        assert_eq!("players[0].ships[1].hp", err.path_string());
+        assert_eq!(PathSegment::Field("players"), err.path[0]);
+    }
+
+    #[test]
+    fn test_parse_error_eof() {
+        let err = GameState::parse(&mut Tokens::from_line("1 0 1 3 10.0")).unwrap_err();
+        assert_eq!(None, err.found);
+        assert_eq!(5, err.index);
+        assert_eq!("players[0].ships[0].position.y", err.path_string());
+
+        // A bogus count runs out of input instead of allocating for it.
+        let err = GameState::parse(&mut Tokens::from_line("18446744073709551615")).unwrap_err();
+        assert_eq!(None, err.found);
+        assert_eq!("players[0].id", err.path_string());
     }
 }
@@ -1,36 +1,55 @@
+use std::collections::HashMap;
 use super::entity::Ship;
-use super::parse::Decodable;
+use super::parse::{Decodable, ParseContext, ParseResult, Tokens};
+use super::encode::Encodable;
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct Player {
     pub id: i32,
-    pub ships: Box<[Ship]>,
+    /// Only set through `new`, which indexes them.
+    ships: Box<[Ship]>,
+    /// Position of each ship in `ships` by ship id.
+    ship_index: HashMap<i32, usize>,
 }
 
 impl Player {
+    pub fn new(id: i32, ships: Box<[Ship]>) -> Self {
+        let ship_index = ships
+            .iter()
+            .enumerate()
+            .map(|(i, ship)| (ship.id, i))
+            .collect();
+        Player {
+            id,
+            ships,
+            ship_index,
+        }
+    }
+
     pub fn all_ships(&self) -> &[Ship] {
         &self.ships
     }
 
     pub fn get_ship(&self, ship_id: i32) -> Option<&Ship> {
-        for ship in self.all_ships() {
-            if ship.id == ship_id {
-                return Some(ship)
-            }
-        }
-        None
+        self.ship_index.get(&ship_id).map(|&i| &self.ships[i])
     }
 }
 
 impl Decodable for Player {
-    fn parse<'a, I>(tokens: &mut I) -> Self
+    fn parse<'a, I>(tokens: &mut Tokens<I>) -> ParseResult<Self>
     where
         I: Iterator<Item = &'a str>,
     {
+        let id = i32::parse(tokens).field("id")?;
+        let ships = Box::parse(tokens).field("ships")?;
 
-        let id = i32::parse(tokens);
-        let ships = Box::parse(tokens);
+        Ok(Player::new(id, ships))
+    }
+}
 
-        Self { id, ships }
+impl Encodable for Player {
+    fn encode(&self, tokens: &mut Vec<String>) {
+        self.id.encode(tokens);
+        self.ships.encode(tokens);
     }
 }
@@ -0,0 +1,600 @@
+use std::f64;
+use std::collections::{HashMap, HashSet};
+use std::cmp::Ordering::Equal;
+use super::collision::collision_time;
+use super::command::{decode_commands, Command};
+use super::encode::Encodable;
+use super::constants::{BASE_PRODUCTIVITY, BASE_SHIP_HEALTH, DOCK_TURNS, MAX_SPEED, SHIP_COST,
+                       SHIP_RADIUS, SPAWN_RADIUS, WEAPON_COOLDOWN, WEAPON_DAMAGE, WEAPON_RADIUS};
+use super::entity::{DockingStatus, Entity, GameState, Planet, Position, Ship};
+use super::parse::{Decodable, ParseResult, Tokens};
+use super::player::Player;
+
+/// Offline stand-in for the Halite II engine. Each call to `step` advances a
+/// `GameState` by one turn using the commands issued by every player.
+///
+/// A turn is resolved in the same order as the engine: weapon cooldowns,
+/// commands, movement with collisions, weapons, planet explosions, docking
+/// progress and finally production.
+#[derive(Debug)]
+pub struct Simulator {
+    pub map_width: i32,
+    pub map_height: i32,
+    next_ship_id: i32,
+}
+
+/// Something two entities ran into while moving during a turn.
+#[derive(Debug, Clone, Copy)]
+enum Event {
+    ShipShip(usize, usize),
+    ShipPlanet(usize, usize),
+}
+
+impl Simulator {
+    pub fn new(map_width: i32, map_height: i32, state: &GameState) -> Self {
+        Simulator {
+            map_width,
+            map_height,
+            next_ship_id: next_ship_id(state),
+        }
+    }
+
+    /// Apply `commands[i]`, the commands of `state.players[i]`, and produce
+    /// the state of the next turn. Commands for ships a player does not own
+    /// and any command after the first for a ship are ignored.
+    pub fn step(&mut self, state: &GameState, commands: &[Vec<Command>]) -> GameState {
+        self.next_ship_id = self.next_ship_id.max(next_ship_id(state));
+
+        let mut ships: Vec<(usize, Ship)> = Vec::new();
+        for (owner, player) in state.players.iter().enumerate() {
+            for ship in player.all_ships() {
+                ships.push((owner, ship.clone()));
+            }
+        }
+        let mut planets: Vec<Planet> = state.planets.to_vec();
+
+        for &mut (_, ref mut ship) in ships.iter_mut() {
+            ship.cooldown = (ship.cooldown - 1).max(0);
+        }
+
+        self.process_commands(state, &mut ships, &mut planets, commands);
+        self.process_movement(&mut ships, &mut planets);
+        process_attacks(&mut ships);
+        process_explosions(&mut ships, &mut planets);
+        remove_dead(&mut ships, &mut planets);
+        process_docking(&mut ships, &mut planets);
+        self.process_production(state, &mut ships, &mut planets);
+
+        let players = state
+            .players
+            .iter()
+            .enumerate()
+            .map(|(i, player)| {
+                Player::new(
+                    player.id,
+                    ships
+                        .iter()
+                        .filter(|&&(owner, _)| owner == i)
+                        .map(|(_, ship)| ship.clone())
+                        .collect::<Vec<_>>()
+                        .into_boxed_slice(),
+                )
+            })
+            .collect::<Vec<_>>()
+            .into_boxed_slice();
+
+        GameState {
+            players,
+            planets: planets.into_boxed_slice(),
+        }
+    }
+
+    /// Read a map as the width and height followed by its first frame.
+    pub fn load(input: &str) -> ParseResult<(Simulator, GameState)> {
+        let mut tokens = Tokens::from_line(input);
+        let width = i32::parse(&mut tokens)?;
+        let height = i32::parse(&mut tokens)?;
+        let state = GameState::parse(&mut tokens)?;
+        Ok((Simulator::new(width, height, &state), state))
+    }
+
+    /// Play from `state` until at most one player is left or `max_turns` have
+    /// passed, returning the last state. `bots[i]` plays `state.players[i]`:
+    /// it is handed each frame as the engine would send it and answers with
+    /// the line of commands the engine would read back. A line that doesn't
+    /// parse counts as no commands.
+    pub fn play<F: FnMut(&str) -> String>(
+        &mut self,
+        state: GameState,
+        bots: &mut [F],
+        max_turns: usize,
+    ) -> GameState {
+        let mut state = state;
+        for _ in 0..max_turns {
+            if Simulator::living_players(&state).len() <= 1 {
+                break;
+            }
+            let frame = Simulator::frame(&state);
+            let commands: Vec<Vec<Command>> = bots
+                .iter_mut()
+                .map(|bot| decode_commands(&bot(&frame)).unwrap_or_default())
+                .collect();
+            state = self.step(&state, &commands);
+        }
+        state
+    }
+
+    /// Render a state as the frame the engine sends to every bot each turn.
+    pub fn frame(state: &GameState) -> String {
+        format!("{}\n", state.to_line())
+    }
+
+    /// Ids of the players that still have ships or planets.
+    pub fn living_players(state: &GameState) -> Vec<i32> {
+        state
+            .players
+            .iter()
+            .filter(|player| {
+                !player.all_ships().is_empty() ||
+                    state.planets.iter().any(|planet| planet.owner == Some(player.id))
+            })
+            .map(|player| player.id)
+            .collect()
+    }
+
+    fn process_commands(
+        &self,
+        state: &GameState,
+        ships: &mut [(usize, Ship)],
+        planets: &mut [Planet],
+        commands: &[Vec<Command>],
+    ) {
+        let mut docking: Vec<(usize, usize)> = Vec::new();
+        for (owner, player_commands) in commands.iter().enumerate() {
+            let mut seen = HashSet::new();
+            for command in player_commands {
+                let ship_id = match *command {
+                    Command::Dock(s, _) | Command::Undock(s) | Command::Thrust(s, _, _) => s,
+                    Command::Nop => continue,
+                };
+                if !seen.insert(ship_id) {
+                    continue;
+                }
+                let index = match ships
+                    .iter()
+                    .position(|&(o, ref ship)| o == owner && ship.id == ship_id) {
+                    Some(index) => index,
+                    None => continue,
+                };
+                let ship = &mut ships[index].1;
+
+                match *command {
+                    Command::Thrust(_, magnitude, angle) => {
+                        if ship.docking_status != DockingStatus::UNDOCKED {
+                            continue;
+                        }
+                        let magnitude = magnitude.clamp(0, MAX_SPEED) as f64;
+                        let angle = (angle as f64).to_radians();
+                        ship.velocity_x = magnitude * angle.cos();
+                        ship.velocity_y = magnitude * angle.sin();
+                    }
+                    Command::Dock(_, planet_id) => {
+                        if ship.docking_status != DockingStatus::UNDOCKED {
+                            continue;
+                        }
+                        if let Some(p) = planets.iter().position(|p| p.id == planet_id) {
+                            if ship.can_dock(&planets[p]) {
+                                docking.push((index, p));
+                            }
+                        }
+                    }
+                    Command::Undock(_) => {
+                        if ship.docking_status == DockingStatus::DOCKED {
+                            ship.docking_status = DockingStatus::UNDOCKING;
+                            ship.progress = DOCK_TURNS;
+                        }
+                    }
+                    Command::Nop => (),
+                }
+            }
+        }
+
+        for (p, planet) in planets.iter_mut().enumerate() {
+            let requests: Vec<usize> = docking
+                .iter()
+                .filter(|&&(_, planet_index)| planet_index == p)
+                .map(|&(ship_index, _)| ship_index)
+                .collect();
+            if requests.is_empty() {
+                continue;
+            }
+
+            // Players racing for the same free planet all fail to dock.
+            let owners: HashSet<usize> = requests.iter().map(|&s| ships[s].0).collect();
+            if planet.owner.is_none() && owners.len() > 1 {
+                continue;
+            }
+
+            for ship_index in requests {
+                let (owner, ref mut ship) = ships[ship_index];
+                let player_id = state.players[owner].id;
+                if planet.is_full() || planet.owner.is_some_and(|id| id != player_id) {
+                    continue;
+                }
+                ship.docking_status = DockingStatus::DOCKING;
+                ship.docked_planet = Some(planet.id);
+                ship.progress = DOCK_TURNS;
+                ship.velocity_x = 0.0;
+                ship.velocity_y = 0.0;
+                planet.owner = Some(player_id);
+                let mut docked = planet.docked_ships.to_vec();
+                docked.push(ship.id);
+                planet.docked_ships = docked.into_boxed_slice();
+            }
+        }
+    }
+
+    fn process_movement(&self, ships: &mut [(usize, Ship)], planets: &mut [Planet]) {
+        let mut events: Vec<(f64, Event)> = Vec::new();
+        for i in 0..ships.len() {
+            let a = &ships[i].1;
+            for (j, (_, b)) in ships.iter().enumerate().skip(i + 1) {
+                if let Some(t) = collision_time(
+                    a.position,
+                    (a.velocity_x, a.velocity_y),
+                    b.position,
+                    (b.velocity_x, b.velocity_y),
+                    a.radius() + b.radius(),
+                ) {
+                    events.push((t, Event::ShipShip(i, j)));
+                }
+            }
+            for (p, planet) in planets.iter().enumerate() {
+                if let Some(t) = collision_time(
+                    a.position,
+                    (a.velocity_x, a.velocity_y),
+                    planet.position,
+                    (0.0, 0.0),
+                    a.radius() + planet.radius(),
+                ) {
+                    events.push((t, Event::ShipPlanet(i, p)));
+                }
+            }
+        }
+        events.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Equal));
+
+        for &(_, event) in &events {
+            match event {
+                Event::ShipShip(i, j) => {
+                    let (hp_i, hp_j) = (ships[i].1.hp, ships[j].1.hp);
+                    if hp_i > 0 && hp_j > 0 {
+                        ships[i].1.hp -= hp_j;
+                        ships[j].1.hp -= hp_i;
+                    }
+                }
+                Event::ShipPlanet(i, p) => {
+                    let hp = ships[i].1.hp;
+                    if hp > 0 && planets[p].hp > 0 {
+                        planets[p].hp -= hp;
+                        ships[i].1.hp = 0;
+                    }
+                }
+            }
+        }
+
+        let (width, height) = (self.map_width as f64, self.map_height as f64);
+        for &mut (_, ref mut ship) in ships.iter_mut() {
+            let Position(x, y) = ship.position;
+            ship.position = Position(x + ship.velocity_x, y + ship.velocity_y);
+            ship.velocity_x = 0.0;
+            ship.velocity_y = 0.0;
+
+            let Position(x, y) = ship.position;
+            if x < 0.0 || y < 0.0 || x > width || y > height {
+                ship.hp = 0;
+            }
+        }
+    }
+
+    fn process_production(
+        &mut self,
+        state: &GameState,
+        ships: &mut Vec<(usize, Ship)>,
+        planets: &mut [Planet],
+    ) {
+        let center = Position(self.map_width as f64 / 2.0, self.map_height as f64 / 2.0);
+        for planet in planets.iter_mut() {
+            let owner = match planet.owner.and_then(|id| {
+                state.players.iter().position(|player| player.id == id)
+            }) {
+                Some(owner) => owner,
+                None => continue,
+            };
+
+            let docked = ships
+                .iter()
+                .filter(|&(_, ship)| {
+                    ship.docked_planet == Some(planet.id) &&
+                        ship.docking_status == DockingStatus::DOCKED
+                })
+                .count() as i32;
+            let produced = (docked * BASE_PRODUCTIVITY).min(planet.remaining_resources);
+            planet.current_production += produced;
+            planet.remaining_resources -= produced;
+
+            while planet.current_production >= SHIP_COST {
+                let position = match spawn_position(planet, &center, ships) {
+                    Some(position) => position,
+                    None => break,
+                };
+                planet.current_production -= SHIP_COST;
+                ships.push((owner, new_ship(self.next_ship_id, position)));
+                self.next_ship_id += 1;
+            }
+        }
+    }
+}
+
+fn next_ship_id(state: &GameState) -> i32 {
+    state
+        .players
+        .iter()
+        .flat_map(|player| player.all_ships().iter())
+        .map(|ship| ship.id + 1)
+        .max()
+        .unwrap_or(0)
+}
+
+fn new_ship(id: i32, position: Position) -> Ship {
+    Ship {
+        id,
+        position,
+        hp: BASE_SHIP_HEALTH,
+        velocity_x: 0.0,
+        velocity_y: 0.0,
+        docking_status: DockingStatus::UNDOCKED,
+        docked_planet: None,
+        progress: 0,
+        cooldown: 0,
+    }
+}
+
+/// Undocked ships with a ready weapon split `WEAPON_DAMAGE` across every
+/// enemy ship within `WEAPON_RADIUS`. All ships fire simultaneously, and the
+/// shares each ship takes are added up before rounding like the engine does.
+pub fn process_attacks(ships: &mut [(usize, Ship)]) {
+    let mut damage = vec![0.0; ships.len()];
+    let mut fired = vec![false; ships.len()];
+
+    for (i, &(owner, ref ship)) in ships.iter().enumerate() {
+        if ship.hp <= 0 || ship.cooldown > 0 ||
+            ship.docking_status != DockingStatus::UNDOCKED
+        {
+            continue;
+        }
+        let targets: Vec<usize> = ships
+            .iter()
+            .enumerate()
+            .filter(|&(_, &(o, ref other))| {
+                o != owner && other.hp > 0 && ship.distance_with(other) <= WEAPON_RADIUS
+            })
+            .map(|(j, _)| j)
+            .collect();
+        if targets.is_empty() {
+            continue;
+        }
+        fired[i] = true;
+        for j in &targets {
+            damage[*j] += WEAPON_DAMAGE as f64 / targets.len() as f64;
+        }
+    }
+
+    for (i, &mut (_, ref mut ship)) in ships.iter_mut().enumerate() {
+        ship.hp -= damage[i].round() as i32;
+        if fired[i] {
+            ship.cooldown = WEAPON_COOLDOWN;
+        }
+    }
+}
+
+/// Destroyed planets damage every ship and planet within `EXPLOSION_RADIUS`
+/// of their surface, which may in turn destroy other planets.
+fn process_explosions(ships: &mut [(usize, Ship)], planets: &mut Vec<Planet>) {
+    let mut exploded = HashSet::new();
+    loop {
+        let dying: Vec<usize> = (0..planets.len())
+            .filter(|&p| planets[p].hp <= 0 && !exploded.contains(&p))
+            .collect();
+        if dying.is_empty() {
+            break;
+        }
+
+        for p in dying {
+            exploded.insert(p);
+            let planet = planets[p].clone();
+            for &mut (_, ref mut ship) in ships.iter_mut() {
+                if ship.docked_planet == Some(planet.id) {
+                    ship.hp = 0;
+                } else {
+                    ship.hp -= planet.explosion_damage(ship);
+                }
+            }
+            for (q, other) in planets.iter_mut().enumerate() {
+                if q != p {
+                    other.hp -= planet.explosion_damage(other);
+                }
+            }
+        }
+    }
+
+    planets.retain(|planet| planet.hp > 0);
+}
+
+/// Drop destroyed ships and release the planets they were docked to.
+fn remove_dead(ships: &mut Vec<(usize, Ship)>, planets: &mut [Planet]) {
+    let dead: HashSet<i32> = ships
+        .iter()
+        .filter(|(_, ship)| ship.hp <= 0)
+        .map(|(_, ship)| ship.id)
+        .collect();
+    ships.retain(|(_, ship)| ship.hp > 0);
+
+    for planet in planets.iter_mut() {
+        release(planet, |id| dead.contains(&id));
+    }
+}
+
+/// Remove the matching docked ships from a planet, giving up ownership once
+/// nobody is left.
+fn release<F: Fn(i32) -> bool>(planet: &mut Planet, remove: F) {
+    let docked: Vec<i32> = planet
+        .docked_ships
+        .iter()
+        .cloned()
+        .filter(|&id| !remove(id))
+        .collect();
+    if docked.is_empty() {
+        planet.owner = None;
+        planet.current_production = 0;
+    }
+    planet.docked_ships = docked.into_boxed_slice();
+}
+
+fn process_docking(ships: &mut [(usize, Ship)], planets: &mut [Planet]) {
+    let mut undocked: HashMap<i32, Vec<i32>> = HashMap::new();
+    for &mut (_, ref mut ship) in ships.iter_mut() {
+        match ship.docking_status {
+            DockingStatus::DOCKING => {
+                ship.progress -= 1;
+                if ship.progress <= 0 {
+                    ship.progress = 0;
+                    ship.docking_status = DockingStatus::DOCKED;
+                }
+            }
+            DockingStatus::UNDOCKING => {
+                ship.progress -= 1;
+                if ship.progress <= 0 {
+                    if let Some(planet_id) = ship.docked_planet {
+                        undocked.entry(planet_id).or_default().push(ship.id);
+                    }
+                    ship.progress = 0;
+                    ship.docking_status = DockingStatus::UNDOCKED;
+                    ship.docked_planet = None;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    for planet in planets.iter_mut() {
+        if let Some(ids) = undocked.get(&planet.id) {
+            release(planet, |id| ids.contains(&id));
+        }
+    }
+}
+
+/// Find a free spot `SPAWN_RADIUS` from the planet's surface, preferring the
+/// side facing the map center.
+fn spawn_position(planet: &Planet, center: &Position, ships: &[(usize, Ship)]) -> Option<Position> {
+    let base = planet.angle_with(center).to_radians();
+    let distance = planet.radius + SPAWN_RADIUS;
+    let step = f64::consts::PI / 18.0;
+
+    for attempt in 0..36 {
+        let offset = ((attempt + 1) / 2) as f64 * step;
+        let angle = if attempt % 2 == 0 { base + offset } else { base - offset };
+        let position = Position::from_origin(planet, angle, distance);
+        let occupied = ships
+            .iter()
+            .any(|(_, ship)| ship.distance_with(&position) < 2.0 * SHIP_RADIUS);
+        if !occupied {
+            return Some(position);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Simulator;
+    use hlt::command::Command;
+    use hlt::constants::{BASE_SHIP_HEALTH, DOCK_TURNS, SHIP_COST};
+    use hlt::entity::{DockingStatus, Position};
+    use hlt::fixture::{planet, ship, state};
+
+    #[test]
+    fn test_thrust() {
+        let state = state(vec![vec![ship(0, 10.0, 10.0)]], vec![]);
+        let mut sim = Simulator::new(100, 100, &state);
+        let next = sim.step(&state, &[vec![Command::Thrust(0, 7, 90)]]);
+        assert_eq!(Position(10.0, 17.0), next.players[0].all_ships()[0].position);
+    }
+
+    #[test]
+    fn test_head_on_collision() {
+        let state = state(
+            vec![vec![ship(0, 10.0, 10.0)], vec![ship(1, 20.0, 10.0)]],
+            vec![],
+        );
+        let mut sim = Simulator::new(100, 100, &state);
+        let next = sim.step(
+            &state,
+            &[vec![Command::Thrust(0, 7, 0)], vec![Command::Thrust(1, 7, 180)]],
+        );
+        assert!(next.players[0].all_ships().is_empty());
+        assert!(next.players[1].all_ships().is_empty());
+    }
+
+    #[test]
+    fn test_weapons_split_damage() {
+        let state = state(
+            vec![vec![ship(0, 10.0, 10.0)], vec![ship(1, 13.0, 10.0), ship(2, 10.0, 13.0)]],
+            vec![],
+        );
+        let mut sim = Simulator::new(100, 100, &state);
+        let next = sim.step(&state, &[vec![], vec![]]);
+        assert_eq!(BASE_SHIP_HEALTH - 2 * 64, next.players[0].all_ships()[0].hp);
+        assert_eq!(BASE_SHIP_HEALTH - 32, next.players[1].all_ships()[0].hp);
+        assert_eq!(1, next.players[1].all_ships()[0].cooldown);
+    }
+
+    #[test]
+    fn test_weapons_round_damage() {
+        // Both of ours fire at three enemies, which take 2 * 64 / 3 each.
+        let state = state(
+            vec![
+                vec![ship(0, 10.0, 10.0), ship(1, 10.0, 11.0)],
+                vec![ship(2, 13.0, 10.0), ship(3, 13.0, 11.0), ship(4, 12.0, 12.0)],
+            ],
+            vec![],
+        );
+        let mut sim = Simulator::new(100, 100, &state);
+        let next = sim.step(&state, &[vec![], vec![]]);
+        assert_eq!(BASE_SHIP_HEALTH - 3 * 32, next.players[0].all_ships()[0].hp);
+        for ship in next.players[1].all_ships() {
+            assert_eq!(BASE_SHIP_HEALTH - 43, ship.hp);
+        }
+    }
+
+    #[test]
+    fn test_dock_and_produce() {
+        let mut state = state(vec![vec![ship(0, 10.0, 10.0)]], vec![planet(0, 10.0, 18.0, 5.0)]);
+        let mut sim = Simulator::new(100, 100, &state);
+        state = sim.step(&state, &[vec![Command::Dock(0, 0)]]);
+        assert_eq!(DockingStatus::DOCKING, state.players[0].all_ships()[0].docking_status);
+        assert_eq!(Some(0), state.planets[0].owner);
+
+        for _ in 1..DOCK_TURNS {
+            state = sim.step(&state, &[vec![]]);
+        }
+        assert_eq!(DockingStatus::DOCKED, state.players[0].all_ships()[0].docking_status);
+
+        while state.players[0].all_ships().len() == 1 {
+            assert!(state.planets[0].current_production < SHIP_COST);
+            state = sim.step(&state, &[vec![]]);
+        }
+        let spawned = &state.players[0].all_ships()[1];
+        assert_eq!(1, spawned.id);
+        assert_eq!(DockingStatus::UNDOCKED, spawned.docking_status);
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use super::entity::{Entity, Position};
+
+/// Uniform grid bucketing entities by the cells their bounding box covers.
+/// Entities are referred to by an index into a list kept by the caller, and
+/// queries return candidate indices that still need an exact test.
+#[derive(Debug)]
+pub struct SpatialGrid {
+    cell_size: f64,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    min_cell: (i32, i32),
+    max_cell: (i32, i32),
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f64) -> Self {
+        SpatialGrid {
+            cell_size,
+            cells: HashMap::new(),
+            min_cell: (0, 0),
+            max_cell: (0, 0),
+        }
+    }
+
+    fn cell(&self, x: f64, y: f64) -> (i32, i32) {
+        (
+            (x / self.cell_size).floor() as i32,
+            (y / self.cell_size).floor() as i32,
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Distance from `pos` to the furthest corner of the occupied cells, a
+    /// circle query with a larger radius covers every entity.
+    pub fn furthest_distance(&self, pos: &Position) -> f64 {
+        let &Position(x, y) = pos;
+        let min_x = self.min_cell.0 as f64 * self.cell_size;
+        let min_y = self.min_cell.1 as f64 * self.cell_size;
+        let max_x = (self.max_cell.0 + 1) as f64 * self.cell_size;
+        let max_y = (self.max_cell.1 + 1) as f64 * self.cell_size;
+        let dx = (x - min_x).abs().max((x - max_x).abs());
+        let dy = (y - min_y).abs().max((y - max_y).abs());
+        f64::sqrt(dx * dx + dy * dy)
+    }
+
+    pub fn insert<E: Entity>(&mut self, index: usize, entity: &E) {
+        let Position(x, y) = entity.position();
+        let r = entity.radius();
+        let (min_x, min_y) = self.cell(x - r, y - r);
+        let (max_x, max_y) = self.cell(x + r, y + r);
+
+        if self.cells.is_empty() {
+            self.min_cell = (min_x, min_y);
+            self.max_cell = (max_x, max_y);
+        } else {
+            self.min_cell = (self.min_cell.0.min(min_x), self.min_cell.1.min(min_y));
+            self.max_cell = (self.max_cell.0.max(max_x), self.max_cell.1.max(max_y));
+        }
+
+        for cx in min_x..(max_x + 1) {
+            for cy in min_y..(max_y + 1) {
+                self.cells.entry((cx, cy)).or_default().push(index);
+            }
+        }
+    }
+
+    /// Indices of entities whose bounding box overlaps the given box.
+    fn query_box(&self, min: (f64, f64), max: (f64, f64)) -> Vec<usize> {
+        let (min_x, min_y) = self.cell(min.0, min.1);
+        let (max_x, max_y) = self.cell(max.0, max.1);
+        let min_x = min_x.max(self.min_cell.0);
+        let min_y = min_y.max(self.min_cell.1);
+        let max_x = max_x.min(self.max_cell.0);
+        let max_y = max_y.min(self.max_cell.1);
+
+        let mut result = Vec::new();
+        for cx in min_x..(max_x + 1) {
+            for cy in min_y..(max_y + 1) {
+                if let Some(indices) = self.cells.get(&(cx, cy)) {
+                    result.extend_from_slice(indices);
+                }
+            }
+        }
+        result.sort();
+        result.dedup();
+        result
+    }
+
+    /// Candidates that may lie within `radius` of `pos`.
+    pub fn query_circle(&self, pos: &Position, radius: f64) -> Vec<usize> {
+        let &Position(x, y) = pos;
+        self.query_box((x - radius, y - radius), (x + radius, y + radius))
+    }
+
+    /// Candidates that may lie within `radius` of the segment from `start` to
+    /// `end`.
+    pub fn query_segment(&self, start: &Position, end: &Position, radius: f64) -> Vec<usize> {
+        let (&Position(x1, y1), &Position(x2, y2)) = (start, end);
+        self.query_box(
+            (x1.min(x2) - radius, y1.min(y2) - radius),
+            (x1.max(x2) + radius, y1.max(y2) + radius),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hlt::entity::Position;
+    use super::SpatialGrid;
+
+    #[test]
+    fn test_queries() {
+        let points = [Position(1.0, 1.0), Position(15.0, 1.0), Position(40.0, 40.0)];
+        let mut grid = SpatialGrid::new(8.0);
+        for (i, point) in points.iter().enumerate() {
+            grid.insert(i, point);
+        }
+
+        assert_eq!(vec![0], grid.query_circle(&Position(2.0, 2.0), 3.0));
+        assert_eq!(vec![0, 1], grid.query_circle(&Position(8.0, 1.0), 7.0));
+        assert_eq!(
+            vec![0, 1],
+            grid.query_segment(&Position(0.0, 0.0), &Position(16.0, 0.0), 1.0)
+        );
+        assert!(grid.query_circle(&Position(100.0, 100.0), 5.0).is_empty());
+        assert!(grid.furthest_distance(&Position(0.0, 0.0)) >= 40.0 * 2f64.sqrt());
+    }
+}
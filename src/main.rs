@@ -1,55 +1,107 @@
 #[macro_use]
 extern crate log;
+extern crate rand;
 extern crate simplelog;
 
 mod hlt;
 mod rusty;
 
-use std::fs::File;
+use std::env;
+use std::fs::{self, File};
+use std::time::Duration;
 use simplelog::*;
 
-use hlt::game::Game;
+use hlt::command::CommandQueue;
+use hlt::game::{Game, GameError};
 use rusty::Bot;
+use rusty::budget::TurnBudget;
+use rusty::constants::TURN_TIME_LIMIT;
+use rusty::offline;
+use rusty::random::configured_seed;
 
 fn main() {
-    let name = "Rusty";
+    // Play a game against ourselves through the simulator instead
+    let args: Vec<String> = env::args().collect();
+    if let Some(index) = args.iter().position(|arg| arg == "--offline") {
+        let path = args.get(index + 1).expect("Missing map file after --offline");
+        play_offline(path);
+        return;
+    }
 
     // Initiailize the game
-    let game = Game::new();
-
-    // Initiailize the bot
-    let mut rusty = Bot::new(&game);
+    let mut game = Game::new();
 
     // Initialize logging
     CombinedLogger::init(vec![
         WriteLogger::new(
             LogLevelFilter::Trace,
             Config::default(),
-            File::create(&format!("log_{}", game.my_id)).expect(
+            File::create(format!("log_{}", game.my_id)).expect(
                 "Unable to open log file",
             )
         ),
     ]).unwrap();
 
+    // Initiailize the bot
+    let mut rusty = Bot::new(&game, configured_seed());
+
     // Retrieve the first game map
-    let game_map = game.update_map();
+    let game_map = game.update_map().unwrap_or_else(|err| {
+        error!("Unable to parse the initial map: {}", err);
+        panic!("Unable to parse the initial map: {}", err)
+    });
 
     // You can preprocess things here,
     // you have 60 seconds...
     rusty.initialize(&game_map);
 
     // Once you are done, send a "ready to work"
-    game.send_ready(&rusty.name);
+    game.send_ready(&rusty.name).expect("Unable to send ready");
 
-    let mut command_queue = Vec::new();
+    let mut command_queue = CommandQueue::new();
     loop {
         // Update the game state
-        let game_map = game.update_map();
+        match game.update_map() {
+            Ok(game_map) => {
+                let mut budget = TurnBudget::new(Duration::from_millis(TURN_TIME_LIMIT));
+                rusty.play_round(&game_map, &mut command_queue, &mut budget);
 
-        rusty.play_round(&game_map, &mut command_queue);
+                // Drop anything the engine would reject before sending
+                for err in command_queue.validate(&game_map) {
+                    warn!("Dropping invalid command: {}", err);
+                }
+            }
+            Err(GameError::Parse(err)) => error!("Skipping turn, unable to parse map: {}", err),
+            Err(GameError::Io(err)) => {
+                info!("Game over: {}", err);
+                break;
+            }
+        }
 
         // Send our commands to the game
-        game.send_command_queue(&command_queue);
+        game.send_command_queue(command_queue.commands()).expect("Unable to send commands");
         command_queue.clear();
     }
 }
+
+/// Play the map in `path`, its width and height followed by the first frame,
+/// with one copy of the bot per player and log who is left at the end.
+fn play_offline(path: &str) {
+    CombinedLogger::init(vec![
+        WriteLogger::new(
+            LogLevelFilter::Trace,
+            Config::default(),
+            File::create("log_offline").expect("Unable to open log file"),
+        ),
+    ]).unwrap();
+
+    let input = fs::read_to_string(path).expect("Unable to read map file");
+    match offline::play(&input, configured_seed()) {
+        Ok(state) => {
+            let players = hlt::simulator::Simulator::living_players(&state);
+            info!("Game over, players left: {:?}", players);
+            println!("Players left: {:?}", players);
+        }
+        Err(err) => panic!("Unable to play offline game: {}", err),
+    }
+}
@@ -0,0 +1,228 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter, Result};
+
+use hlt::entity::{DockingStatus, Ship};
+use hlt::game_map::GameMap;
+
+use super::behavior::Behavior;
+use super::constants::{DEFENSE_RADIUS, MAX_DEFENSE_SHARE, MAX_SABOTEURS, SABOTAGE_SHARE,
+                       SABOTAGE_START_TURN};
+
+/// Roles in the order they are filled, and the role of a new ship when no
+/// role is short.
+const ROLES: [Behavior; 5] = [
+    Behavior::Defense,
+    Behavior::Settler,
+    Behavior::Sabotage,
+    Behavior::Attacker,
+    Behavior::Raider,
+];
+
+/// Number of undocked units per role.
+#[derive(Debug, Default, PartialEq)]
+pub struct Roles {
+    counts: HashMap<Behavior, usize>,
+}
+
+impl Roles {
+    pub fn new() -> Self {
+        Roles { counts: HashMap::new() }
+    }
+
+    pub fn get(&self, role: Behavior) -> usize {
+        self.counts.get(&role).cloned().unwrap_or(0)
+    }
+
+    pub fn add(&mut self, role: Behavior) {
+        *self.counts.entry(role).or_insert(0) += 1;
+    }
+
+    pub fn remove(&mut self, role: Behavior) {
+        if let Some(count) = self.counts.get_mut(&role) {
+            *count = count.saturating_sub(1);
+        }
+    }
+
+    /// How many more units a role needs to reach `wanted`.
+    pub fn shortage(&self, wanted: &Roles, role: Behavior) -> usize {
+        wanted.get(role).saturating_sub(self.get(role))
+    }
+
+    /// The role furthest below `wanted`, if any is.
+    pub fn most_needed(&self, wanted: &Roles) -> Option<Behavior> {
+        ROLES
+            .iter()
+            .cloned()
+            .filter(|&role| self.shortage(wanted, role) > 0)
+            .max_by_key(|&role| self.shortage(wanted, role))
+    }
+}
+
+impl Display for Roles {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        let roles: Vec<String> = ROLES
+            .iter()
+            .map(|&role| format!("{} {}", role, self.get(role)))
+            .collect();
+        write!(f, "{}", roles.join(", "))
+    }
+}
+
+/// Facts about the game the allocation is based on.
+#[derive(Debug, PartialEq)]
+pub struct Situation {
+    /// Docking spots left on planets that are free or ours.
+    pub free_spots: usize,
+    /// Enemy ships heading for our planets.
+    pub threats: usize,
+    /// Fraction of all docked ships that are ours.
+    pub production_share: f64,
+    /// Whether any planet belongs to an enemy.
+    pub enemy_planets: bool,
+}
+
+impl Situation {
+    pub fn new(game_map: &GameMap) -> Self {
+        let me = game_map.me();
+        let planets = game_map.all_planets().iter().filter(|planet| !planet.is_dead());
+
+        let mut free_spots = 0;
+        let mut threats = HashSet::new();
+        let mut enemy_planets = false;
+        for planet in planets {
+            match planet.owner {
+                Some(owner) if owner != me.id => enemy_planets = true,
+                _ => free_spots += planet.num_docking_spots.saturating_sub(planet.docked_ships.len()),
+            }
+            if planet.owner == Some(me.id) {
+                for enemy in game_map.ships_within(planet, planet.radius + DEFENSE_RADIUS) {
+                    if me.get_ship(enemy.id).is_none() &&
+                        enemy.docking_status == DockingStatus::UNDOCKED
+                    {
+                        threats.insert(enemy.id);
+                    }
+                }
+            }
+        }
+
+        let producing = |ship: &&Ship| ship.docking_status == DockingStatus::DOCKED;
+        let ours = me.all_ships().iter().filter(producing).count();
+        let total = game_map.all_ships().filter(producing).count();
+        let production_share = if total == 0 {
+            1.0 / game_map.all_players().len().max(1) as f64
+        } else {
+            ours as f64 / total as f64
+        };
+
+        Situation {
+            free_spots,
+            threats: threats.len(),
+            production_share,
+            enemy_planets,
+        }
+    }
+}
+
+/// How many of `available` undocked units each role should get on turn
+/// `round`. Threats to our planets are answered first, then free docking
+/// spots are filled. A fleet losing the production race late in the game
+/// sends a few saboteurs, and the rest fight, mostly going after ships the
+/// further behind we are.
+pub fn allocate(situation: &Situation, round: i32, available: usize) -> Roles {
+    let mut roles = Roles::new();
+    let mut remaining = available;
+    let assign = |roles: &mut Roles, role: Behavior, count: usize, remaining: &mut usize| {
+        let count = count.min(*remaining);
+        for _ in 0..count {
+            roles.add(role);
+        }
+        *remaining -= count;
+    };
+
+    let defense = situation
+        .threats
+        .min((available as f64 * MAX_DEFENSE_SHARE).ceil() as usize);
+    assign(&mut roles, Behavior::Defense, defense, &mut remaining);
+    assign(&mut roles, Behavior::Settler, situation.free_spots, &mut remaining);
+
+    if round >= SABOTAGE_START_TURN && situation.production_share < SABOTAGE_SHARE {
+        assign(&mut roles, Behavior::Sabotage, MAX_SABOTEURS, &mut remaining);
+    }
+
+    let attackers = if situation.enemy_planets {
+        (remaining as f64 * (1.0 - situation.production_share)).round() as usize
+    } else {
+        remaining
+    };
+    assign(&mut roles, Behavior::Attacker, attackers, &mut remaining);
+    let raiders = remaining;
+    assign(&mut roles, Behavior::Raider, raiders, &mut remaining);
+    roles
+}
+
+#[cfg(test)]
+mod tests {
+    use hlt::entity::Planet;
+    use hlt::fixture::{docked, game, owned, planet, ship, state};
+    use rusty::behavior::Behavior;
+    use super::{allocate, Situation};
+
+    #[test]
+    fn test_situation() {
+        // We own planet 0 with 1 of 3 spots taken, the enemy owns planet 1
+        // with 2 spots taken and has ship 3 near planet 0.
+        let frame = state(
+            vec![
+                vec![docked(ship(0, 50.0, 50.0), 0), ship(1, 20.0, 20.0)],
+                vec![
+                    docked(ship(2, 100.0, 100.0), 1),
+                    docked(ship(3, 100.0, 104.0), 1),
+                    ship(4, 50.0, 60.0),
+                ],
+            ],
+            vec![
+                owned(planet(0, 50.0, 45.0, 4.0), 0, &[0]),
+                owned(Planet { num_docking_spots: 2, ..planet(1, 100.0, 110.0, 5.0) }, 1, &[2, 3]),
+            ],
+        );
+        let situation = Situation::new(&game(200, 200, &[frame]).update_map().unwrap());
+
+        assert_eq!(
+            Situation {
+                free_spots: 2,
+                threats: 1,
+                production_share: 1.0 / 3.0,
+                enemy_planets: true,
+            },
+            situation
+        );
+    }
+
+    #[test]
+    fn test_allocate() {
+        let situation = Situation {
+            free_spots: 3,
+            threats: 1,
+            production_share: 0.25,
+            enemy_planets: true,
+        };
+        let roles = allocate(&situation, 10, 8);
+        assert_eq!(1, roles.get(Behavior::Defense));
+        assert_eq!(3, roles.get(Behavior::Settler));
+        assert_eq!(0, roles.get(Behavior::Sabotage));
+        assert_eq!(3, roles.get(Behavior::Attacker));
+        assert_eq!(1, roles.get(Behavior::Raider));
+
+        // Late and behind, a couple of ships go for the enemy planets.
+        let roles = allocate(&situation, 200, 8);
+        assert_eq!(2, roles.get(Behavior::Sabotage));
+        assert_eq!("defense 1, settler 3, sabotage 2, attacker 2, raider 0", roles.to_string());
+
+        let roles = allocate(&situation, 10, 2);
+        assert_eq!((1, 1, 0), (
+            roles.get(Behavior::Defense),
+            roles.get(Behavior::Settler),
+            roles.get(Behavior::Attacker)
+        ));
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::{HashMap, VecDeque};
+use std::cmp::Ordering::Equal;
+
+use hlt::collision::intersect_segment_circle;
+use hlt::entity::{Entity, Planet, Position};
+use hlt::game_map::GameMap;
+
+use super::constants::{CHOKEPOINT_DISTANCE, CLUSTER_DISTANCE, EXPANSION_SPOT_VALUE};
+
+/// Static facts about the map, computed once during the preprocessing window
+/// and consulted by behaviors every turn.
+#[derive(Debug, Default)]
+pub struct MapAnalysis {
+    my_id: i32,
+    /// Distance between the centers of every pair of planets, keyed by the
+    /// smaller id first.
+    distances: HashMap<(i32, i32), f64>,
+    /// Groups of planets whose surfaces are within `CLUSTER_DISTANCE` of a
+    /// neighbour in the same group.
+    pub clusters: Vec<Vec<i32>>,
+    /// Center of each player's starting ships by player id.
+    pub spawns: HashMap<i32, Position>,
+    /// Planet ids from the most to the least attractive to settle.
+    pub expansion_order: Vec<i32>,
+    /// Planets lying between our spawn and an enemy spawn.
+    pub chokepoints: Vec<i32>,
+}
+
+impl MapAnalysis {
+    pub fn new(game_map: &GameMap) -> Self {
+        let planets = game_map.all_planets();
+        let my_id = game_map.me().id;
+
+        let mut distances = HashMap::new();
+        for a in planets {
+            for b in planets.iter().filter(|b| a.id < b.id) {
+                distances.insert((a.id, b.id), a.distance_with(b));
+            }
+        }
+
+        let mut spawns = HashMap::new();
+        for player in game_map.all_players() {
+            let ships = player.all_ships();
+            if ships.is_empty() {
+                continue;
+            }
+            let (x, y) = ships.iter().fold((0.0, 0.0), |(x, y), ship| {
+                let Position(ship_x, ship_y) = ship.position();
+                (x + ship_x, y + ship_y)
+            });
+            let count = ships.len() as f64;
+            spawns.insert(player.id, Position(x / count, y / count));
+        }
+
+        let mut analysis = MapAnalysis {
+            my_id,
+            distances,
+            clusters: Vec::new(),
+            spawns,
+            expansion_order: Vec::new(),
+            chokepoints: Vec::new(),
+        };
+        analysis.clusters = analysis.find_clusters(planets);
+        analysis.expansion_order = analysis.rank_expansion(planets);
+        analysis.chokepoints = analysis.find_chokepoints(planets);
+        analysis
+    }
+
+    /// Distance between the centers of two planets.
+    pub fn distance(&self, a: i32, b: i32) -> Option<f64> {
+        if a == b {
+            return Some(0.0);
+        }
+        self.distances.get(&(a.min(b), a.max(b))).cloned()
+    }
+
+    /// Index into `clusters` of the cluster containing the planet.
+    pub fn cluster_of(&self, planet_id: i32) -> Option<usize> {
+        self.clusters.iter().position(|cluster| cluster.contains(&planet_id))
+    }
+
+    pub fn my_spawn(&self) -> Option<Position> {
+        self.spawns.get(&self.my_id).cloned()
+    }
+
+    /// Spawns of the other players, ordered by player id.
+    pub fn enemy_spawns(&self) -> Vec<(i32, Position)> {
+        let mut spawns: Vec<(i32, Position)> = self.spawns
+            .iter()
+            .filter(|&(&id, _)| id != self.my_id)
+            .map(|(&id, &pos)| (id, pos))
+            .collect();
+        spawns.sort_by_key(|&(id, _)| id);
+        spawns
+    }
+
+    /// Position of the planet in `expansion_order`, lower is better.
+    pub fn expansion_rank(&self, planet_id: i32) -> Option<usize> {
+        self.expansion_order.iter().position(|&id| id == planet_id)
+    }
+
+    pub fn is_chokepoint(&self, planet_id: i32) -> bool {
+        self.chokepoints.contains(&planet_id)
+    }
+
+    /// Close planets with many docking spots come first. Planets nearer to an
+    /// enemy spawn than to ours are pushed back by the difference.
+    fn rank_expansion(&self, planets: &[Planet]) -> Vec<i32> {
+        let my_spawn = match self.my_spawn() {
+            Some(spawn) => spawn,
+            None => return planets.iter().map(|planet| planet.id).collect(),
+        };
+        let enemies = self.enemy_spawns();
+
+        let mut scored: Vec<(f64, i32)> = planets
+            .iter()
+            .map(|planet| {
+                let ours = my_spawn.distance_with(planet);
+                let theirs = enemies
+                    .iter()
+                    .map(|&(_, spawn)| spawn.distance_with(planet))
+                    .fold(f64::INFINITY, f64::min);
+                let mut score = ours - EXPANSION_SPOT_VALUE * planet.num_docking_spots as f64;
+                if theirs < ours {
+                    score += ours - theirs;
+                }
+                (score, planet.id)
+            })
+            .collect();
+        scored.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Equal));
+        scored.into_iter().map(|(_, id)| id).collect()
+    }
+
+    /// Group planets whose surfaces are within `CLUSTER_DISTANCE` of each other.
+    fn find_clusters(&self, planets: &[Planet]) -> Vec<Vec<i32>> {
+        let mut assigned = vec![false; planets.len()];
+        let mut clusters = Vec::new();
+
+        for start in 0..planets.len() {
+            if assigned[start] {
+                continue;
+            }
+            assigned[start] = true;
+            let mut cluster = Vec::new();
+            let mut queue = VecDeque::new();
+            queue.push_back(start);
+
+            while let Some(i) = queue.pop_front() {
+                cluster.push(planets[i].id);
+                for j in 0..planets.len() {
+                    let (a, b) = (&planets[i], &planets[j]);
+                    let gap = self.distance(a.id, b.id)
+                        .map_or(f64::INFINITY, |distance| distance - a.radius - b.radius);
+                    if !assigned[j] && gap <= CLUSTER_DISTANCE {
+                        assigned[j] = true;
+                        queue.push_back(j);
+                    }
+                }
+            }
+            clusters.push(cluster);
+        }
+        clusters
+    }
+
+    fn find_chokepoints(&self, planets: &[Planet]) -> Vec<i32> {
+        let my_spawn = match self.my_spawn() {
+            Some(spawn) => spawn,
+            None => return Vec::new(),
+        };
+        let enemies = self.enemy_spawns();
+
+        planets
+            .iter()
+            .filter(|planet| {
+                enemies.iter().any(|&(_, spawn)| {
+                    intersect_segment_circle(&my_spawn, &spawn, *planet, CHOKEPOINT_DISTANCE)
+                })
+            })
+            .map(|planet| planet.id)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hlt::entity::Planet;
+    use hlt::fixture::{game_map, planet, ship, state};
+    use super::MapAnalysis;
+
+    #[test]
+    fn test_analysis() {
+        // We start at (10, 50) and the enemy at (90, 50). Planets 0 and 1 sit
+        // close together near us, planet 2 is in the middle of the map.
+        let game_map = game_map(state(
+            vec![vec![ship(0, 10.0, 50.0)], vec![ship(1, 90.0, 50.0)]],
+            vec![
+                planet(0, 25.0, 50.0, 4.0),
+                Planet { num_docking_spots: 2, ..planet(1, 25.0, 62.0, 4.0) },
+                Planet { num_docking_spots: 6, ..planet(2, 50.0, 50.0, 6.0) },
+            ],
+        ));
+        let analysis = MapAnalysis::new(&game_map);
+
+        assert_eq!(Some(12.0), analysis.distance(1, 0));
+        assert_eq!(analysis.cluster_of(0), analysis.cluster_of(1));
+        assert!(analysis.cluster_of(0) != analysis.cluster_of(2));
+        assert_eq!(vec![0, 1, 2], analysis.expansion_order);
+        assert!(analysis.is_chokepoint(0));
+        assert!(analysis.is_chokepoint(2));
+        assert!(!analysis.is_chokepoint(1));
+    }
+}
@@ -0,0 +1,232 @@
+use std::cmp::Ordering::Equal;
+use std::f64::consts::PI;
+
+use hlt::command::Command;
+use hlt::constants::{MAX_SPEED, WEAPON_RADIUS};
+use hlt::entity::{DockingStatus, Entity, Position, Ship};
+use hlt::game_map::GameMap;
+
+use rusty::combat::{engagement, Engagement};
+use rusty::constants::{ATTACK_MARGIN, COMBAT_RADIUS, DOCKED_TARGET_BONUS, GUARDED_TARGET_PENALTY,
+                       MIN_PLANET_DISTANCE, RETREAT_DIRECTIONS};
+use super::{Behavior, Context, Navigator, UnitBehavior};
+
+/// Hunts enemy ships, docked ones first, without running into larger groups.
+#[derive(Debug, Default)]
+pub struct Attacker {
+    pub enemy: Option<i32>,
+}
+
+impl UnitBehavior for Attacker {
+    fn kind(&self) -> Behavior {
+        Behavior::Attacker
+    }
+
+    fn update(&mut self, ship: &Ship, ctx: &Context) -> Option<Behavior> {
+        debug!("Ship {}: updating with attacker settings", ship.id);
+        let game_map = ctx.game_map;
+        let me = game_map.me().id;
+
+        let alive = self.enemy
+            .and_then(|id| game_map.owner_of_ship(id))
+            .is_some_and(|owner| owner.id != me);
+        if alive {
+            return None;
+        }
+
+        self.enemy = game_map
+            .all_players()
+            .iter()
+            .filter(|player| player.id != me)
+            .flat_map(|player| player.all_ships().iter())
+            .map(|enemy| (attack_score(ship, enemy, game_map), enemy.id))
+            .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Equal))
+            .map(|(_, id)| id);
+        None
+    }
+
+    fn execute(&mut self, ship: &Ship, ctx: &Context, nav: &mut Navigator) -> Option<Command> {
+        let enemy = self.enemy.and_then(|id| ctx.game_map.get_ship(id))?;
+        // Lead a moving enemy by where it will be next turn
+        let mut ahead = enemy.clone();
+        ahead.position = ctx.tracker.predict(enemy, 1);
+        let pos = attack_position(ship, &ahead, ctx);
+        nav.navigate_to(ship, pos, ctx.game_map)
+    }
+}
+
+/// Lower is better. Close enemies come first, docked ones can't shoot back
+/// and stop production when destroyed, ones whose fight we would lose are
+/// avoided.
+fn attack_score(ship: &Ship, enemy: &Ship, game_map: &GameMap) -> f64 {
+    let mut score = ship.distance_with(enemy);
+    if enemy.docking_status == DockingStatus::DOCKED ||
+        enemy.docking_status == DockingStatus::DOCKING
+    {
+        score -= DOCKED_TARGET_BONUS;
+    }
+    let approach = ship.closest_point_to(enemy, WEAPON_RADIUS - ATTACK_MARGIN);
+    let (ours, theirs) = fight_at(ship, approach, game_map);
+    if engagement(&ours, &theirs) != Engagement::Engage {
+        let armed = theirs
+            .iter()
+            .filter(|other| other.docking_status == DockingStatus::UNDOCKED)
+            .count();
+        score += GUARDED_TARGET_PENALTY * armed as f64;
+    }
+    score
+}
+
+/// The ships taking part in a fight with `ship` at `pos`: it and our other
+/// undocked ships, and every enemy ship.
+fn fight_at(ship: &Ship, pos: Position, game_map: &GameMap) -> (Vec<Ship>, Vec<Ship>) {
+    let mut ours = vec![ship.clone()];
+    ours[0].position = pos;
+    let mut theirs = Vec::new();
+    for other in game_map.ships_within(&pos, COMBAT_RADIUS) {
+        if game_map.me().get_ship(other.id).is_none() {
+            theirs.push(other.clone());
+        } else if other.id != ship.id && other.docking_status == DockingStatus::UNDOCKED {
+            ours.push(other.clone());
+        }
+    }
+    (ours, theirs)
+}
+
+/// Where an attacker should head this turn. The fight around the enemy is
+/// simulated with the attacker already there: it closes to within weapon
+/// range when that goes our way, waits just out of the enemies' reach when
+/// it is close or its weapon is cooling down, and falls back when it would
+/// be beaten.
+fn attack_position(ship: &Ship, enemy: &Ship, ctx: &Context) -> Position {
+    let approach = ship.closest_point_to(enemy, WEAPON_RADIUS - ATTACK_MARGIN);
+    let (ours, theirs) = fight_at(ship, approach, ctx.game_map);
+
+    let decision = if ship.cooldown > 0 {
+        Engagement::Wait
+    } else {
+        engagement(&ours, &theirs)
+    };
+    if decision != Engagement::Engage {
+        debug!(
+            "Ship {}: {:?} against {} ({} enemies vs {} allies)",
+            ship.id,
+            decision,
+            enemy.id,
+            theirs.len(),
+            ours.len() - 1
+        );
+    }
+    match decision {
+        Engagement::Engage => approach,
+        Engagement::Wait => ship.closest_point_to(enemy, WEAPON_RADIUS + MAX_SPEED as f64 + ATTACK_MARGIN),
+        Engagement::Retreat => retreat_position(ship, enemy, ctx),
+    }
+}
+
+/// A turn's flight away from `enemy`, along the safest path to our closest
+/// planet when that leads away, or else towards where our firepower
+/// outweighs theirs the most.
+fn retreat_position(ship: &Ship, enemy: &Ship, ctx: &Context) -> Position {
+    let me = ctx.game_map.me().id;
+    let harbour = ctx.game_map
+        .all_planets()
+        .iter()
+        .filter(|planet| planet.owner == Some(me) && !planet.is_dead())
+        .min_by(|a, b| ship.distance_with(*a).total_cmp(&ship.distance_with(*b)));
+    let step = harbour
+        .and_then(|planet| ctx.influence.safest_path(ship, &ship.closest_point_to(planet, MIN_PLANET_DISTANCE)))
+        .and_then(|path| {
+            let last = path.last().cloned();
+            // Cells are smaller than a turn's flight, head for the first
+            // waypoint beyond a turn's flight
+            path.into_iter().find(|pos| ship.distance_with(pos) >= MAX_SPEED as f64).or(last)
+        })
+        .map(|waypoint| {
+            let distance = ship.distance_with(&waypoint).min(MAX_SPEED as f64);
+            Position::from_origin(ship, ship.angle_with(&waypoint).to_radians(), distance)
+        })
+        .filter(|pos| pos.distance_with(enemy) > ship.distance_with(enemy));
+    if let Some(step) = step {
+        return step;
+    }
+
+    let balance = |pos: &Position| ctx.influence.support_at(pos) - ctx.influence.threat_at(pos);
+    (0..RETREAT_DIRECTIONS)
+        .map(|i| {
+            let angle = 2.0 * PI * i as f64 / RETREAT_DIRECTIONS as f64;
+            Position::from_origin(ship, angle, MAX_SPEED as f64)
+        })
+        .filter(|pos| ctx.game_map.contains(pos) && pos.distance_with(enemy) > ship.distance_with(enemy))
+        .max_by(|a, b| {
+            balance(a)
+                .total_cmp(&balance(b))
+                .then_with(|| a.distance_with(enemy).total_cmp(&b.distance_with(enemy)))
+        })
+        .unwrap_or_else(|| ship.closest_point_to(enemy, WEAPON_RADIUS + MAX_SPEED as f64 + ATTACK_MARGIN))
+}
+
+#[cfg(test)]
+mod tests {
+    use hlt::entity::{Entity, Position};
+    use hlt::constants::{MAX_SPEED, WEAPON_RADIUS};
+    use hlt::fixture::{docked, game_map, owned, planet, ship, state};
+    use rusty::behavior::{TestContext, UnitBehavior};
+    use super::{attack_position, retreat_position, Attacker};
+
+    #[test]
+    fn test_attacker() {
+        // Our ship 0 at (10, 50). Enemy ship 1 is undocked at (30, 50), ship 2
+        // is docked at (40, 60) and ships 3 to 5 are undocked at (90, 50).
+        let game_map = game_map(state(
+            vec![
+                vec![ship(0, 10.0, 50.0)],
+                vec![
+                    ship(1, 30.0, 50.0),
+                    docked(ship(2, 40.0, 60.0), 0),
+                    ship(3, 90.0, 50.0),
+                    ship(4, 90.0, 51.0),
+                    ship(5, 90.0, 49.0),
+                ],
+            ],
+            vec![owned(planet(0, 40.0, 70.0, 5.0), 1, &[2])],
+        ));
+        let parts = TestContext::new(&game_map);
+        let ctx = parts.context(&game_map);
+        let ship = game_map.get_ship(0).unwrap();
+
+        let mut attacker = Attacker::default();
+        assert_eq!(None, attacker.update(ship, &ctx));
+        assert_eq!(Some(2), attacker.enemy);
+
+        // Closing in on a lone enemy, falling back from a group of three.
+        let lone = game_map.get_ship(1).unwrap();
+        let pos = attack_position(ship, lone, &ctx);
+        assert!(pos.distance_with(lone) < WEAPON_RADIUS);
+        let group = game_map.get_ship(4).unwrap();
+        let pos = attack_position(ship, group, &ctx);
+        assert!(pos.distance_with(group) > ship.distance_with(group));
+    }
+
+    #[test]
+    fn test_retreat() {
+        // Our ship 0 at (50, 50) faces enemy ships 1 to 3 at (60, 50) and
+        // falls back towards our planet 0 at (20, 50).
+        let game_map = game_map(state(
+            vec![
+                vec![ship(0, 50.0, 50.0)],
+                vec![ship(1, 60.0, 50.0), ship(2, 60.0, 51.0), ship(3, 60.0, 49.0)],
+            ],
+            vec![owned(planet(0, 20.0, 50.0, 5.0), 0, &[])],
+        ));
+        let parts = TestContext::new(&game_map);
+        let ctx = parts.context(&game_map);
+        let ship = game_map.get_ship(0).unwrap();
+        let enemy = game_map.get_ship(1).unwrap();
+
+        let pos = retreat_position(ship, enemy, &ctx);
+        let harbour = Position(20.0, 50.0);
+        assert!(pos.distance_with(&harbour) < ship.distance_with(&harbour) - MAX_SPEED as f64 + 1.0);
+        assert!(pos.distance_with(enemy) > ship.distance_with(enemy));
+    }
+}
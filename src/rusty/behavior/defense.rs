@@ -0,0 +1,188 @@
+use std::cmp::Ordering::Equal;
+use std::f64;
+use std::f64::consts::PI;
+
+use hlt::command::Command;
+use hlt::constants::{MAX_SPEED, WEAPON_RADIUS};
+use hlt::entity::{DockingStatus, Entity, Planet, Position, Ship};
+use hlt::game_map::GameMap;
+
+use rusty::constants::{ATTACK_MARGIN, DEFENSE_HOLD_DISTANCE, DEFENSE_RADIUS};
+use rusty::tracker::Target;
+use super::{Behavior, Context, Navigator, UnitBehavior};
+
+/// Guards our planets, intercepting enemies before they reach the ships
+/// docked there.
+#[derive(Debug, Default)]
+pub struct Defense {
+    pub planet: Option<i32>,
+    pub enemy: Option<i32>,
+}
+
+impl UnitBehavior for Defense {
+    fn kind(&self) -> Behavior {
+        Behavior::Defense
+    }
+
+    fn update(&mut self, ship: &Ship, ctx: &Context) -> Option<Behavior> {
+        debug!("Ship {}: updating with defense settings", ship.id);
+        let game_map = ctx.game_map;
+        let me = game_map.me();
+
+        let owned: Vec<&Planet> = game_map
+            .all_planets()
+            .iter()
+            .filter(|planet| planet.owner == Some(me.id) && !planet.is_dead())
+            .collect();
+        if owned.is_empty() {
+            // Nothing left to guard
+            return Some(Behavior::Attacker);
+        }
+
+        // Enemies closest to one of our planets come first, then the ones
+        // closest to us
+        let threat = owned
+            .iter()
+            .flat_map(|&planet| {
+                game_map
+                    .ships_within(planet, planet.radius + DEFENSE_RADIUS)
+                    .into_iter()
+                    .filter(|enemy| {
+                        me.get_ship(enemy.id).is_none() &&
+                            enemy.docking_status == DockingStatus::UNDOCKED
+                    })
+                    .map(move |enemy| (planet, enemy))
+            })
+            .min_by(|&(a_planet, a), &(b_planet, b)| {
+                let score_a = a.distance_with(a_planet) - a_planet.radius + ship.distance_with(a) / 2.0;
+                let score_b = b.distance_with(b_planet) - b_planet.radius + ship.distance_with(b) / 2.0;
+                score_a.partial_cmp(&score_b).unwrap_or(Equal)
+            });
+
+        let (planet, enemy) = match threat {
+            Some((planet, enemy)) => (planet.id, Some(enemy.id)),
+            None => {
+                // Wait by the planet the enemy is closest to, chokepoints
+                // on their way to us first
+                let planet = owned
+                    .iter()
+                    .map(|planet| {
+                        let distance = game_map
+                            .nearest_enemy_ship(*planet)
+                            .map_or(f64::INFINITY, |enemy| enemy.distance_with(*planet));
+                        (!ctx.analysis.is_chokepoint(planet.id), distance, planet.id)
+                    })
+                    .min_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.total_cmp(&b.1)))
+                    .map(|(_, _, id)| id)
+                    .unwrap_or(owned[0].id);
+                (planet, None)
+            }
+        };
+
+        self.planet = Some(planet);
+        self.enemy = enemy;
+        None
+    }
+
+    fn execute(&mut self, ship: &Ship, ctx: &Context, nav: &mut Navigator) -> Option<Command> {
+        let game_map = ctx.game_map;
+        let planet = self.planet.and_then(|id| game_map.get_planet(id))?;
+        let pos = match self.enemy.and_then(|id| game_map.get_ship(id)) {
+            Some(enemy) => {
+                let target = ctx.tracker.likely_target(enemy.id, game_map);
+                intercept_position(ship, enemy, planet, target, game_map)
+            }
+            None => hold_position(planet, game_map),
+        };
+        nav.navigate_to(ship, pos, game_map)
+    }
+}
+
+/// Where a defender should head to cut off `enemy` before it gets within
+/// weapon range of the ships docked at `planet`. The enemy is assumed to fly
+/// straight at full speed at the docked ship its course points to, or else
+/// the closest one. When it can't be reached in time the defender stands
+/// between the two.
+fn intercept_position(
+    ship: &Ship,
+    enemy: &Ship,
+    planet: &Planet,
+    target: Option<Target>,
+    game_map: &GameMap,
+) -> Position {
+    let docked = game_map.ships_docked_at(planet.id);
+    let aimed_at = docked
+        .iter()
+        .find(|victim| target == Some(Target::Ship(victim.id)))
+        .cloned();
+    let victim = aimed_at
+        .or_else(|| {
+            docked.into_iter().min_by(|a, b| {
+                a.distance_with(enemy)
+                    .partial_cmp(&b.distance_with(enemy))
+                    .unwrap_or(Equal)
+            })
+        })
+        .map_or_else(|| enemy.closest_point_to(planet, 0.0), |victim| victim.position());
+
+    let distance = enemy.distance_with(&victim);
+    let angle = enemy.angle_with(&victim).to_radians();
+    let mut along = 0.0;
+    while along < distance - WEAPON_RADIUS {
+        let point = Position::from_origin(enemy, angle, along);
+        if ship.distance_with(&point) - WEAPON_RADIUS <= along {
+            return ship.closest_point_to(&point, WEAPON_RADIUS - ATTACK_MARGIN);
+        }
+        along += MAX_SPEED as f64;
+    }
+    Position::from_origin(&victim, angle + PI, WEAPON_RADIUS - ATTACK_MARGIN)
+}
+
+/// Where a defender waits when no enemy is around: off the planet's surface
+/// on the side of the closest enemy.
+fn hold_position(planet: &Planet, game_map: &GameMap) -> Position {
+    let angle = game_map
+        .nearest_enemy_ship(planet)
+        .map_or(0.0, |enemy| planet.angle_with(enemy).to_radians());
+    Position::from_origin(planet, angle, planet.radius + DEFENSE_HOLD_DISTANCE)
+}
+
+#[cfg(test)]
+mod tests {
+    use hlt::entity::{Entity, Position};
+    use hlt::constants::{MAX_SPEED, WEAPON_RADIUS};
+    use hlt::fixture::{docked, game_map, owned, planet, ship, state};
+    use rusty::behavior::{TestContext, UnitBehavior};
+    use super::{hold_position, intercept_position, Defense};
+
+    #[test]
+    fn test_defense() {
+        // We own planet 0 at (50, 50) with ship 1 docked at (57, 50). Our
+        // defender 0 waits at (70, 40) while enemy ship 2 comes from (80, 50).
+        let game_map = game_map(state(
+            vec![
+                vec![ship(0, 70.0, 40.0), docked(ship(1, 57.0, 50.0), 0)],
+                vec![ship(2, 80.0, 50.0)],
+            ],
+            vec![owned(planet(0, 50.0, 50.0, 5.0), 0, &[1])],
+        ));
+        let parts = TestContext::new(&game_map);
+        let ctx = parts.context(&game_map);
+        let ship = game_map.get_ship(0).unwrap();
+        let planet = game_map.get_planet(0).unwrap();
+        let enemy = game_map.get_ship(2).unwrap();
+
+        let mut defense = Defense::default();
+        assert_eq!(None, defense.update(ship, &ctx));
+        assert_eq!((Some(0), Some(2)), (defense.planet, defense.enemy));
+
+        // Cut off on the way, before the enemy is in range of ship 1.
+        let pos = intercept_position(ship, enemy, planet, None, &game_map);
+        assert!(pos.distance_with(&Position(57.0, 50.0)) > WEAPON_RADIUS);
+        assert!(pos.distance_with(enemy) < MAX_SPEED as f64 + WEAPON_RADIUS);
+
+        // With nobody near, wait on the side the enemy comes from.
+        let pos = hold_position(planet, &game_map);
+        assert!(pos.0 > 50.0 && (pos.1 - 50.0).abs() < 0.001);
+    }
+}
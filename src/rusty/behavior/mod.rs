@@ -0,0 +1,230 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter, Result};
+
+use hlt::command::Command;
+use hlt::entity::{Position, Ship};
+use hlt::game_map::GameMap;
+use hlt::navigation::NavigationPlan;
+
+use super::analysis::MapAnalysis;
+use super::events::FleetEvent;
+use super::influence::InfluenceMap;
+use super::pathfinding::Route;
+use super::reservation::Reservations;
+use super::tracker::EnemyTracker;
+
+mod settler;
+mod raider;
+mod attacker;
+mod defense;
+mod sabotage;
+
+pub use self::settler::Settler;
+pub use self::raider::Raider;
+pub use self::attacker::Attacker;
+pub use self::defense::Defense;
+pub use self::sabotage::Sabotage;
+
+/// The roles a unit can play.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum Behavior {
+    Attacker,
+    Defense,
+    Raider,
+    Settler,
+    Sabotage,
+}
+
+impl Display for Behavior {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        let string = match *self {
+            Behavior::Attacker => "attacker",
+            Behavior::Defense => "defense",
+            Behavior::Raider => "raider",
+            Behavior::Settler => "settler",
+            Behavior::Sabotage => "sabotage",
+        };
+        write!(f, "{}", string)
+    }
+}
+
+/// What behaviors get to look at while deciding.
+pub struct Context<'a> {
+    pub game_map: &'a GameMap,
+    pub analysis: &'a MapAnalysis,
+    /// Docking spots claimed by the fleet, shared by every unit.
+    pub reservations: &'a RefCell<Reservations>,
+    /// Where enemy ships have been and seem to be going.
+    pub tracker: &'a EnemyTracker,
+    /// Firepower of both sides across the map.
+    pub influence: &'a InfluenceMap,
+}
+
+/// What a `Context` borrows besides the map, as at the start of a game.
+#[cfg(test)]
+pub struct TestContext {
+    pub analysis: MapAnalysis,
+    pub reservations: RefCell<Reservations>,
+    pub tracker: EnemyTracker,
+    pub influence: InfluenceMap,
+}
+
+#[cfg(test)]
+impl TestContext {
+    pub fn new(game_map: &GameMap) -> Self {
+        TestContext {
+            analysis: MapAnalysis::default(),
+            reservations: RefCell::new(Reservations::new()),
+            tracker: EnemyTracker::new(),
+            influence: InfluenceMap::new(game_map),
+        }
+    }
+
+    pub fn context<'a>(&'a self, game_map: &'a GameMap) -> Context<'a> {
+        Context {
+            game_map,
+            analysis: &self.analysis,
+            reservations: &self.reservations,
+            tracker: &self.tracker,
+            influence: &self.influence,
+        }
+    }
+}
+
+/// Moves a ship along its route while keeping clear of the rest of the
+/// fleet.
+pub struct Navigator<'a> {
+    pub plan: &'a mut NavigationPlan,
+    route: &'a mut Route,
+    attempts: i32,
+}
+
+impl<'a> Navigator<'a> {
+    pub fn new(plan: &'a mut NavigationPlan, route: &'a mut Route, attempts: i32) -> Self {
+        Navigator {
+            plan,
+            route,
+            attempts,
+        }
+    }
+
+    /// Head for `goal` around planets, planning the route again as needed.
+    pub fn navigate_to(&mut self, ship: &Ship, goal: Position, game_map: &GameMap) -> Option<Command> {
+        let waypoint = self.route.next_waypoint(ship, goal, game_map);
+        self.plan.navigate(ship, &waypoint, game_map, self.attempts)
+    }
+}
+
+/// The logic of one role. Each unit owns its own instance, so state such as
+/// the current target lives in the implementation.
+pub trait UnitBehavior: Debug {
+    /// The role this implements.
+    fn kind(&self) -> Behavior;
+
+    /// Called when a unit takes on this role.
+    fn on_enter(&mut self, _ship: &Ship, _ctx: &Context) {}
+
+    /// Called when a unit leaves this role.
+    fn on_exit(&mut self, _ship: &Ship, _ctx: &Context) {}
+
+    /// Called with the lifecycle events of the unit's ship.
+    fn on_event(&mut self, _event: &FleetEvent, _ctx: &Context) {}
+
+    /// Pick targets for the turn. Returns the role to switch to when this one
+    /// has nothing left to do.
+    fn update(&mut self, ship: &Ship, ctx: &Context) -> Option<Behavior>;
+
+    /// Decide the ship's command for the turn.
+    fn execute(&mut self, ship: &Ship, ctx: &Context, nav: &mut Navigator) -> Option<Command>;
+
+    /// Order in which units are executed, lower goes first.
+    fn priority(&self) -> i32 {
+        2
+    }
+}
+
+/// Creates the implementation of each role.
+pub type Factory = fn() -> Box<dyn UnitBehavior>;
+
+/// The implementation used for every role. Registering a factory for a role
+/// replaces the previous one, a role without one gets the built in
+/// implementation.
+#[derive(Debug)]
+pub struct BehaviorRegistry {
+    factories: HashMap<Behavior, Factory>,
+}
+
+impl BehaviorRegistry {
+    /// A registry without any role registered.
+    pub fn new() -> Self {
+        BehaviorRegistry { factories: HashMap::new() }
+    }
+
+    /// Use `factory` for `kind`. Returns false and keeps the current one when
+    /// the factory implements another role.
+    pub fn register(&mut self, kind: Behavior, factory: Factory) -> bool {
+        let actual = factory().kind();
+        if actual != kind {
+            warn!("Not registering a {} implementation as the {}", actual, kind);
+            return false;
+        }
+        self.factories.insert(kind, factory);
+        true
+    }
+
+    pub fn create(&self, kind: Behavior) -> Box<dyn UnitBehavior> {
+        self.factories.get(&kind).cloned().unwrap_or_else(|| built_in(kind))()
+    }
+}
+
+impl Default for BehaviorRegistry {
+    /// A registry with the built in implementation of every role.
+    fn default() -> Self {
+        let mut registry = BehaviorRegistry::new();
+        for &kind in &[
+            Behavior::Settler,
+            Behavior::Raider,
+            Behavior::Attacker,
+            Behavior::Defense,
+            Behavior::Sabotage,
+        ] {
+            registry.register(kind, built_in(kind));
+        }
+        registry
+    }
+}
+
+/// The implementation of each role that comes with the bot.
+fn built_in(kind: Behavior) -> Factory {
+    match kind {
+        Behavior::Settler => || Box::new(Settler::default()),
+        Behavior::Raider => || Box::new(Raider::default()),
+        Behavior::Attacker => || Box::new(Attacker::default()),
+        Behavior::Defense => || Box::new(Defense::default()),
+        Behavior::Sabotage => || Box::new(Sabotage::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Behavior, BehaviorRegistry, Raider};
+
+    #[test]
+    fn test_registry() {
+        let mut registry = BehaviorRegistry::default();
+        assert_eq!(Behavior::Settler, registry.create(Behavior::Settler).kind());
+        assert_eq!(Behavior::Sabotage, registry.create(Behavior::Sabotage).kind());
+
+        // A raider can't stand in for the settler.
+        assert!(!registry.register(Behavior::Settler, || Box::new(Raider::default())));
+        assert_eq!(Behavior::Settler, registry.create(Behavior::Settler).kind());
+
+        // Swap the raider for another implementation of it.
+        assert!(registry.register(Behavior::Raider, || Box::new(Raider { target: Some(3) })));
+        assert_eq!(Behavior::Raider, registry.create(Behavior::Raider).kind());
+
+        // Roles nobody registered get the built in implementation.
+        assert_eq!(Behavior::Defense, BehaviorRegistry::new().create(Behavior::Defense).kind());
+    }
+}
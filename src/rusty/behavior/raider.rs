@@ -0,0 +1,114 @@
+use std::cmp::Ordering::{Less, Equal, Greater};
+
+use hlt::command::Command;
+use hlt::entity::{Entity, Ship};
+
+use rusty::constants::MIN_PLANET_DISTANCE;
+use rusty::events::FleetEvent;
+use rusty::reservation::claimable;
+use super::{Behavior, Context, Navigator, UnitBehavior};
+
+/// Heads for planets we don't own, docking at free ones and circling behind
+/// enemy ones.
+#[derive(Debug, Default)]
+pub struct Raider {
+    pub target: Option<i32>,
+}
+
+impl UnitBehavior for Raider {
+    fn kind(&self) -> Behavior {
+        Behavior::Raider
+    }
+
+    fn priority(&self) -> i32 {
+        1
+    }
+
+    fn on_exit(&mut self, ship: &Ship, ctx: &Context) {
+        ctx.reservations.borrow_mut().release(ship.id);
+    }
+
+    fn on_event(&mut self, event: &FleetEvent, _ctx: &Context) {
+        if let FleetEvent::TargetLost { .. } = *event {
+            self.target = None;
+        }
+    }
+
+    fn update(&mut self, ship: &Ship, ctx: &Context) -> Option<Behavior> {
+        debug!("Ship {}: updating with raider settings", ship.id);
+        let game_map = ctx.game_map;
+        let me = game_map.me().id;
+
+        let mut reservations = ctx.reservations.borrow_mut();
+
+        let need_new_target = self.target
+            .and_then(|target| game_map.get_planet(target))
+            .is_none_or(|planet| {
+                planet.owner == Some(me) || claimable(planet, me) && !reservations.holds(planet.id, ship.id)
+            });
+
+        if !need_new_target {
+            return None;
+        }
+
+        // Free planets count only while they have a spot no one claimed
+        let planet_iter = game_map.all_planets().iter();
+        let planet = planet_iter
+            .filter(|planet| if let Some(owner) = planet.owner {
+                owner != me
+            } else {
+                reservations.is_available(planet, ship.id)
+            })
+            .min_by(|&a, &b| {
+                let dist_a = ship.distance_with(a);
+                let dist_b = ship.distance_with(b);
+
+                if a.owner.is_none() && b.owner.is_some() ||
+                    a.owner != Some(me) && b.owner == Some(me)
+                {
+                    Less
+                } else if b.owner.is_none() && a.owner.is_some() ||
+                    b.owner != Some(me) && a.owner == Some(me)
+                {
+                    Greater
+                } else if dist_a < dist_b {
+                    Less
+                } else if dist_a == dist_b {
+                    Equal
+                } else {
+                    Greater
+                }
+            });
+
+        match planet {
+            Some(target) => {
+                if claimable(target, me) {
+                    reservations.claim(target, ship.id);
+                } else {
+                    reservations.release(ship.id);
+                }
+                self.target = Some(target.id);
+                None
+            }
+            None => {
+                reservations.release(ship.id);
+                self.target = None;
+                None
+            }
+        }
+    }
+
+    fn execute(&mut self, ship: &Ship, ctx: &Context, nav: &mut Navigator) -> Option<Command> {
+        let target = self.target.and_then(|id| ctx.game_map.get_planet(id))?;
+        if ship.can_dock(target) {
+            debug!("Ship {}: docking with {}", ship.id, target.id);
+            return Some(ship.dock(target));
+        }
+        let pos = if target.owner.is_none() {
+            ship.closest_point_to(target, MIN_PLANET_DISTANCE)
+        } else {
+            ship.furthest_point_to(target, MIN_PLANET_DISTANCE)
+        };
+        nav.navigate_to(ship, pos, ctx.game_map)
+    }
+}
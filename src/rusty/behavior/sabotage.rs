@@ -0,0 +1,140 @@
+use std::cmp::Ordering::Equal;
+
+use hlt::command::Command;
+use hlt::constants::{EXPLOSION_RADIUS, MAX_SHIP_HEALTH, MAX_SPEED};
+use hlt::entity::{Entity, Planet, Ship};
+use hlt::game_map::GameMap;
+use hlt::navigation::Trajectory;
+
+use rusty::constants::{MIN_PLANET_DISTANCE, SABOTAGE_DISTANCE_COST, SABOTAGE_MAX_CRASHES};
+use super::{Behavior, Context, Navigator, UnitBehavior};
+
+/// Crashes into enemy planets whose explosion would cost them more than it
+/// costs us.
+#[derive(Debug, Default)]
+pub struct Sabotage {
+    pub planet: Option<i32>,
+}
+
+impl UnitBehavior for Sabotage {
+    fn kind(&self) -> Behavior {
+        Behavior::Sabotage
+    }
+
+    fn update(&mut self, ship: &Ship, ctx: &Context) -> Option<Behavior> {
+        debug!("Ship {}: updating with sabotage settings", ship.id);
+        let game_map = ctx.game_map;
+        let me = game_map.me().id;
+
+        // Weigh every enemy planet again, its defenders move
+        let planet = game_map
+            .all_planets()
+            .iter()
+            .filter(|planet| !planet.is_dead() && planet.owner.is_some_and(|owner| owner != me))
+            .filter_map(|planet| {
+                sabotage_value(ship, planet, game_map)
+                    .map(|value| (value - SABOTAGE_DISTANCE_COST * ship.distance_with(planet), planet))
+            })
+            .filter(|&(value, _)| value > 0.0)
+            .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Equal));
+
+        match planet {
+            Some((value, planet)) => {
+                debug!("Ship {}: planet {} is worth {} to crash", ship.id, planet.id, value);
+                self.planet = Some(planet.id);
+                None
+            }
+            // Nothing worth the loss, fight the usual way
+            None => Some(Behavior::Attacker),
+        }
+    }
+
+    fn execute(&mut self, ship: &Ship, ctx: &Context, nav: &mut Navigator) -> Option<Command> {
+        let game_map = ctx.game_map;
+        let planet = self.planet.and_then(|id| game_map.get_planet(id))?;
+        let surface = ship.distance_with(planet) - planet.radius;
+        if surface <= MAX_SPEED as f64 + MIN_PLANET_DISTANCE {
+            // Final approach, straight into the planet
+            let command = ship.thrust(MAX_SPEED, ship.angle_with(planet).round() as i32 % 360);
+            let trajectory = Trajectory::of(ship, &command);
+            if let Some(other) = nav.plan.conflict(ship, &trajectory, game_map) {
+                debug!("Ship {}: waiting for ship {} to clear the way", ship.id, other);
+                return None;
+            }
+            debug!("Ship {}: crashing into planet {}", ship.id, planet.id);
+            return Some(command);
+        }
+        let pos = ship.closest_point_to(planet, MIN_PLANET_DISTANCE);
+        nav.navigate_to(ship, pos, game_map)
+    }
+}
+
+/// Expected gain of crashing into `planet`: hit points its explosion takes
+/// from the enemy, less what it takes from us and the ships that have to
+/// crash to bring it down. `None` if it takes too many crashes.
+fn sabotage_value(ship: &Ship, planet: &Planet, game_map: &GameMap) -> Option<f64> {
+    let crashes = (planet.hp + ship.hp - 1) / ship.hp.max(1);
+    if crashes > SABOTAGE_MAX_CRASHES {
+        return None;
+    }
+
+    let me = game_map.me();
+    let mut value = -(crashes * MAX_SHIP_HEALTH) as f64;
+    for other in game_map.ships_within(planet, planet.radius + EXPLOSION_RADIUS + MAX_SPEED as f64) {
+        if other.id == ship.id {
+            continue;
+        }
+        let damage = if other.docked_planet == Some(planet.id) {
+            other.hp
+        } else {
+            planet.explosion_damage(other).min(other.hp)
+        } as f64;
+        if me.get_ship(other.id).is_some() {
+            value -= damage;
+        } else {
+            value += damage;
+        }
+    }
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use hlt::entity::Planet;
+    use hlt::fixture::{docked, game_map, owned, planet, ship, state};
+    use rusty::behavior::{TestContext, UnitBehavior};
+    use super::{sabotage_value, Sabotage};
+
+    #[test]
+    fn test_sabotage() {
+        // Enemy planet 0 at (50, 50) is down to 200 hp with enemy ships 1 to
+        // 3 docked, planet 1 at (20, 80) is healthy. Our ship 0 is at (30, 50).
+        let weakened = owned(planet(0, 50.0, 50.0, 5.0), 1, &[1, 2, 3]);
+        let game_map = game_map(state(
+            vec![
+                vec![ship(0, 30.0, 50.0)],
+                vec![
+                    docked(ship(1, 56.0, 50.0), 0),
+                    docked(ship(2, 50.0, 56.0), 0),
+                    docked(ship(3, 44.0, 50.0), 0),
+                ],
+            ],
+            vec![
+                Planet { hp: 200, ..weakened },
+                owned(planet(1, 20.0, 80.0, 5.0), 1, &[]),
+            ],
+        ));
+        let parts = TestContext::new(&game_map);
+        let ctx = parts.context(&game_map);
+        let ship = game_map.get_ship(0).unwrap();
+
+        // Three docked ships lost for one crash.
+        let value = sabotage_value(ship, game_map.get_planet(0).unwrap(), &game_map);
+        assert_eq!(Some(255.0 * 2.0), value);
+        assert_eq!(None, sabotage_value(ship, game_map.get_planet(1).unwrap(), &game_map));
+
+        let mut sabotage = Sabotage::default();
+        assert_eq!(None, sabotage.update(ship, &ctx));
+        assert_eq!(Some(0), sabotage.planet);
+    }
+}
@@ -0,0 +1,153 @@
+use hlt::command::Command;
+use hlt::entity::{Entity, Ship};
+
+use rusty::constants::{EXPANSION_THREAT_COST, EXPANSION_TIE_DISTANCE, MIN_PLANET_DISTANCE};
+use rusty::events::FleetEvent;
+use rusty::reservation::claimable;
+use super::{Behavior, Context, Navigator, UnitBehavior};
+
+/// Docks at the closest planet with room left. When distances are close it
+/// favours the clusters we already hold, then the map analysis expansion
+/// order.
+#[derive(Debug, Default)]
+pub struct Settler {
+    pub target: Option<i32>,
+}
+
+impl UnitBehavior for Settler {
+    fn kind(&self) -> Behavior {
+        Behavior::Settler
+    }
+
+    fn priority(&self) -> i32 {
+        0
+    }
+
+    fn on_exit(&mut self, ship: &Ship, ctx: &Context) {
+        ctx.reservations.borrow_mut().release(ship.id);
+    }
+
+    fn on_event(&mut self, event: &FleetEvent, _ctx: &Context) {
+        if let FleetEvent::TargetLost { .. } = *event {
+            self.target = None;
+        }
+    }
+
+    fn update(&mut self, ship: &Ship, ctx: &Context) -> Option<Behavior> {
+        debug!("Ship {}: updating with settler settings", ship.id);
+        let game_map = ctx.game_map;
+        let me = game_map.me().id;
+
+        let mut reservations = ctx.reservations.borrow_mut();
+
+        let need_new_target = self.target
+            .and_then(|target| game_map.get_planet(target))
+            .is_none_or(|planet| {
+                planet.owner == Some(me) && planet.is_full() ||
+                    claimable(planet, me) && !reservations.holds(planet.id, ship.id)
+            });
+
+        if !need_new_target {
+            return None;
+        }
+
+        // Settling next to planets we hold keeps the fleet together
+        let home: Vec<usize> = game_map
+            .all_planets()
+            .iter()
+            .filter(|planet| planet.owner == Some(me) && !planet.is_dead())
+            .filter_map(|planet| ctx.analysis.cluster_of(planet.id))
+            .collect();
+
+        // find a new target with a spot no other settler claimed
+        let candidates: Vec<(f64, i32)> = game_map
+            .all_planets()
+            .iter()
+            .filter(|planet| if planet.is_dead() {
+                false
+            } else {
+                planet.owner == Some(me) && !planet.is_full() || planet.owner != Some(me)
+            })
+            .filter(|planet| {
+                !claimable(planet, me) || reservations.is_available(planet, ship.id)
+            })
+            .map(|planet| {
+                // Planets under enemy fire count as further away
+                let distance = ship.distance_with(planet) +
+                    EXPANSION_THREAT_COST * ctx.influence.threat_at(planet);
+                (distance, planet.id)
+            })
+            .collect();
+
+        // Anything about as close as the nearest candidate is a tie
+        let nearest = candidates
+            .iter()
+            .map(|&(distance, _)| distance)
+            .fold(f64::INFINITY, f64::min);
+        self.target = candidates
+            .into_iter()
+            .filter(|&(distance, _)| distance - nearest <= EXPANSION_TIE_DISTANCE)
+            .map(|(distance, id)| {
+                let away = ctx.analysis
+                    .cluster_of(id)
+                    .is_none_or(|cluster| !home.contains(&cluster));
+                let rank = ctx.analysis.expansion_rank(id).unwrap_or(usize::MAX);
+                ((away, rank), distance, id)
+            })
+            .min_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.total_cmp(&b.1)))
+            .map(|(_, _, id)| id);
+
+        match self.target.and_then(|id| game_map.get_planet(id)) {
+            Some(planet) if claimable(planet, me) => {
+                reservations.claim(planet, ship.id);
+            }
+            _ => reservations.release(ship.id),
+        }
+
+        if self.target.is_none() {
+            Some(Behavior::Raider)
+        } else {
+            None
+        }
+    }
+
+    fn execute(&mut self, ship: &Ship, ctx: &Context, nav: &mut Navigator) -> Option<Command> {
+        let target = self.target.and_then(|id| ctx.game_map.get_planet(id))?;
+        if ship.can_dock(target) {
+            debug!("Ship {}: docking with {}", ship.id, target.id);
+            return Some(ship.dock(target));
+        }
+        let pos = ship.closest_point_to(target, MIN_PLANET_DISTANCE);
+        nav.navigate_to(ship, pos, ctx.game_map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hlt::fixture::{game_map, planet, ship, state};
+    use rusty::behavior::{TestContext, UnitBehavior};
+    use super::Settler;
+
+    #[test]
+    fn test_settler() {
+        // Our ship 0 at (10, 50) with planets 20, 26.9 and 27.1 away. Only
+        // the first two are about as close, so the better ranked of them wins
+        // over the best ranked planet 2.
+        let game_map = game_map(state(
+            vec![vec![ship(0, 10.0, 50.0)], vec![ship(1, 90.0, 90.0)]],
+            vec![
+                planet(0, 30.0, 50.0, 3.0),
+                planet(1, 10.0, 76.9, 3.0),
+                planet(2, 10.0, 22.9, 3.0),
+            ],
+        ));
+        let mut parts = TestContext::new(&game_map);
+        parts.analysis.expansion_order = vec![2, 1, 0];
+        let ctx = parts.context(&game_map);
+        let ship = game_map.get_ship(0).unwrap();
+
+        let mut settler = Settler::default();
+        assert_eq!(None, settler.update(ship, &ctx));
+        assert_eq!(Some(1), settler.target);
+    }
+}
@@ -1,15 +1,25 @@
-extern crate rand;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Write};
 
-use std::collections::HashMap;
-
-use hlt::command::Command;
-use hlt::entity::{Entity, DockingStatus};
+use hlt::command::CommandQueue;
+use hlt::entity::Ship;
 use hlt::game::Game;
 use hlt::game_map::GameMap;
+use hlt::navigation::NavigationPlan;
+
 
+use rand::{Rng, XorShiftRng};
 
-use self::rand::{thread_rng, Rng};
-use super::behavior::Behavior;
+use super::analysis::MapAnalysis;
+use super::allocator::{allocate, Roles, Situation};
+use super::behavior::{Behavior, BehaviorRegistry, Context};
+use super::budget::{Pressure, TurnBudget};
+use super::events::{log_event, EventBus, FleetEvent};
+use super::influence::InfluenceMap;
+use super::random::{map_seed, seeded};
+use super::reservation::Reservations;
+use super::tracker::EnemyTracker;
 use super::unit::Unit;
 
 #[derive(Debug)]
@@ -17,69 +27,376 @@ pub struct Bot {
     pub name: String,
     pub round: i32,
     pub fleet: HashMap<i32, Unit>,
+    pub analysis: MapAnalysis,
+    pub behaviors: BehaviorRegistry,
+    pub reservations: RefCell<Reservations>,
+    pub tracker: EnemyTracker,
+    pub influence: InfluenceMap,
+    /// Lifecycle events of the fleet, published at the start of each turn.
+    pub events: EventBus,
+    /// Seed of `rng`, derived from the map on initialization when not given.
+    pub seed: Option<u64>,
+    rng: XorShiftRng,
 }
 
 impl Bot {
-    pub fn new(game: &Game) -> Self {
+    /// Every random decision is drawn from `seed`, so the same frames and
+    /// seed play out the same way as long as the turns don't run short on
+    /// time. Under pressure units fall back to cheaper moves depending on
+    /// the clock, see `TurnBudget`.
+    pub fn new<R: BufRead, W: Write>(game: &Game<R, W>, seed: Option<u64>) -> Self {
+        let mut events = EventBus::new();
+        events.subscribe(Box::new(log_event));
         Bot {
             name: format!("rusty{}", game.my_id),
             round: 0,
             fleet: HashMap::new(),
+            analysis: MapAnalysis::default(),
+            behaviors: BehaviorRegistry::default(),
+            reservations: RefCell::new(Reservations::new()),
+            tracker: EnemyTracker::new(),
+            influence: InfluenceMap::default(),
+            events,
+            seed,
+            rng: seeded(seed.unwrap_or(0)),
         }
     }
 
     pub fn initialize(&mut self, game_map: &GameMap) {
         info!("Initializing bot {}", self.name);
         info!("Initial ship count {}", game_map.me().all_ships().len());
+
+        let seed = self.seed.unwrap_or_else(|| map_seed(game_map));
+        self.seed = Some(seed);
+        self.rng = seeded(seed);
+        info!("Random seed {}", seed);
+
+        self.analysis = MapAnalysis::new(game_map);
+        info!("Planet clusters {:?}", self.analysis.clusters);
+        info!("Spawns {:?}", self.analysis.spawns);
+        info!("Expansion order {:?}", self.analysis.expansion_order);
+        info!("Chokepoints {:?}", self.analysis.chokepoints);
     }
 
-    pub fn play_round(&mut self, game_map: &GameMap, command_queue: &mut Vec<Command>) {
+    pub fn play_round(
+        &mut self,
+        game_map: &GameMap,
+        command_queue: &mut CommandQueue,
+        budget: &mut TurnBudget,
+    ) {
         self.round += 1;
         info!("Playing round {}", self.round + 1);
 
-        self.update_units(&game_map);
+        let delta = game_map.delta();
+        for change in &delta.owners {
+            info!("Planet {} changed owner: {:?} -> {:?}", change.planet, change.from, change.to);
+        }
+        if !delta.is_empty() {
+            debug!("Changes since last turn {:?}", delta);
+        }
+
+        self.tracker.update(game_map);
+        self.influence = InfluenceMap::new(game_map);
+        self.update_units(game_map);
+        budget.checkpoint("update units");
+
+        // Loop over all of our player's ships, most important units first
+        let mut ships: Vec<&Ship> = game_map.me().all_ships().iter().collect();
+        ships.sort_by_key(|ship| self.fleet.get(&ship.id).map(|unit| unit.priority()));
+
+        let ctx = Context {
+            game_map,
+            analysis: &self.analysis,
+            reservations: &self.reservations,
+            tracker: &self.tracker,
+            influence: &self.influence,
+        };
+
+        // Every command is reserved so later ships steer around earlier ones
+        let mut plan = NavigationPlan::new();
+        let mut skipped = 0;
+        for ship in ships {
+            if budget.pressure() == Pressure::Exhausted {
+                skipped += 1;
+                continue;
+            }
 
-        // Loop over all of our player's ships
-        for ship in game_map.me().all_ships() {
             let id = ship.id;
-            let unit = self.fleet.get_mut(&id).expect(&format!(
-                "Unit {} doesn't exist or is dead",
-                id
-            ));
+            let unit = self.fleet.get_mut(&id).unwrap_or_else(|| {
+                panic!("Unit {} doesn't exist or is dead", id)
+            });
 
-            let cmd = unit.execute(&ship, game_map);
+            let cmd = unit.execute(ship, &ctx, &mut plan, budget);
             info!("{}\n executing {:?}", unit.to_string(game_map), cmd);
-            if cmd.is_some() {
-                command_queue.push(cmd.unwrap());
+            if let Some(cmd) = cmd {
+                plan.reserve(ship, &cmd);
+                command_queue.push(cmd);
             }
         }
+        if skipped > 0 {
+            warn!("Out of time, skipped {} units", skipped);
+        }
+        budget.checkpoint("execute units");
     }
 
     fn update_units(&mut self, game_map: &GameMap) {
         debug!("Updating data structures");
-        for ship in game_map.me().all_ships() {
-            let unit = if self.fleet.contains_key(&ship.id) {
-                self.fleet.get_mut(&ship.id).unwrap()
-            } else {
-                let behavior = if self.round < 50 {
-                    Behavior::Raider
-                } else if thread_rng().gen_range(0.0, 100.0) <= 75.0 {
-                    Behavior::Settler
-                } else {
-                    Behavior::Raider
-                };
-                self.fleet.insert(ship.id, Unit::new(ship, behavior));
-                let u = self.fleet.get_mut(&ship.id).unwrap();
-                info!("New unit:\n{}", u.to_string(game_map));
-                u
-            };
-
-            unit.update(ship, game_map);
+        let ships = game_map.me().all_ships();
+
+        // Units whose ship is gone no longer hold anything
+        let mut destroyed: Vec<i32> = self.fleet
+            .keys()
+            .cloned()
+            .filter(|&id| game_map.me().get_ship(id).is_none())
+            .collect();
+        destroyed.sort();
+        let mut dead = HashMap::new();
+        for &id in &destroyed {
+            self.reservations.get_mut().release(id);
+            dead.insert(id, self.fleet.remove(&id).unwrap());
+        }
+
+        let mut events: Vec<FleetEvent> = destroyed.into_iter().map(FleetEvent::Destroyed).collect();
+        for ship in ships {
+            if let Some(unit) = self.fleet.get_mut(&ship.id) {
+                events.extend(unit.track_docking(ship));
+            }
+        }
+        let lost = self.reservations.get_mut().refresh(game_map);
+        events.extend(lost.into_iter().map(|(ship, planet)| FleetEvent::TargetLost { ship, planet }));
+
+        let ctx = Context {
+            game_map,
+            analysis: &self.analysis,
+            reservations: &self.reservations,
+            tracker: &self.tracker,
+            influence: &self.influence,
+        };
+        for event in &events {
+            self.events.publish(event);
+            let id = event.ship_id();
+            if let Some(unit) = self.fleet.get_mut(&id).or_else(|| dead.get_mut(&id)) {
+                unit.notify(event, &ctx);
+            }
+        }
+
+        // Behaviors go first, a unit that gave up on its role this turn keeps
+        // the one it fell back to instead of being moved again below
+        let mut switched = HashSet::new();
+        for ship in ships {
+            if let Some(unit) = self.fleet.get_mut(&ship.id) {
+                let before = unit.behavior();
+                unit.update(ship, &ctx, &self.behaviors);
+                if unit.behavior() != before {
+                    switched.insert(ship.id);
+                }
+            }
+        }
+
+        let undocked: Vec<&Ship> = ships.iter().filter(|ship| !ship.is_docked()).collect();
+        let situation = Situation::new(game_map);
+        let wanted = allocate(&situation, self.round, undocked.len());
+        debug!("{:?}", situation);
+
+        let mut roles = Roles::new();
+        for ship in &undocked {
+            if let Some(unit) = self.fleet.get(&ship.id) {
+                roles.add(unit.behavior());
+            }
+        }
+
+        // New ships take the role the fleet is shortest of
+        for ship in ships {
+            if !self.fleet.contains_key(&ship.id) {
+                let behavior = roles.most_needed(&wanted).unwrap_or(Behavior::Settler);
+                if !ship.is_docked() {
+                    roles.add(behavior);
+                }
+                let mut unit = Unit::new(ship, behavior, &ctx, &self.behaviors);
+                info!("New unit:\n{}", unit.to_string(game_map));
+                let event = FleetEvent::Spawned(ship.id);
+                self.events.publish(&event);
+                unit.notify(&event, &ctx);
+                unit.update(ship, &ctx, &self.behaviors);
+                self.fleet.insert(ship.id, unit);
+            }
+        }
+
+        // Move randomly picked units out of crowded roles into short ones
+        let mut candidates: Vec<&Ship> = undocked
+            .iter()
+            .cloned()
+            .filter(|ship| {
+                let role = self.fleet[&ship.id].behavior();
+                !switched.contains(&ship.id) && roles.get(role) > wanted.get(role)
+            })
+            .collect();
+        self.rng.shuffle(&mut candidates);
+        for ship in candidates {
+            let unit = self.fleet.get_mut(&ship.id).unwrap();
+            let current = unit.behavior();
+            if roles.get(current) <= wanted.get(current) {
+                continue;
+            }
+            match roles.most_needed(&wanted) {
+                Some(behavior) => {
+                    roles.remove(current);
+                    roles.add(behavior);
+                    unit.switch(ship, behavior, &ctx, &self.behaviors);
+                    unit.update(ship, &ctx, &self.behaviors);
+                }
+                None => break,
+            }
+        }
+
+        let mut actual = Roles::new();
+        for ship in ships {
+            let unit = &self.fleet[&ship.id];
+            if !ship.is_docked() {
+                actual.add(unit.behavior());
+            }
             info!(
                 "Updating unit {}\n {}",
                 unit.ship_id,
                 unit.to_string(game_map)
             );
         }
+        info!("Roles wanted: {}", wanted);
+        info!("Roles actual: {}", actual);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+    use hlt::command::{encode_commands, Command, CommandQueue};
+    use hlt::entity::{DockingStatus, GameState, Planet, Ship};
+    use hlt::fixture::{docked, game, owned, planet, ship, state};
+    use rusty::budget::{Pressure, TurnBudget};
+    use rusty::events::FleetEvent;
+    use super::Bot;
+
+    /// Commands sent on each turn of a game on `frames`.
+    fn replay(frames: &[GameState], seed: Option<u64>) -> Vec<String> {
+        let mut game = game(200, 200, frames);
+        let mut bot = Bot::new(&game, seed);
+        bot.initialize(&game.update_map().unwrap());
+
+        let mut turns = Vec::new();
+        while let Ok(game_map) = game.update_map() {
+            let mut queue = CommandQueue::new();
+            let mut budget = TurnBudget::new(Duration::from_secs(60));
+            bot.play_round(&game_map, &mut queue, &mut budget);
+            turns.push(encode_commands(queue.commands()));
+        }
+        turns
+    }
+
+    #[test]
+    fn test_deterministic() {
+        // We hold planet 0 with docked ship 4. On the last turn enemy ship 10
+        // closes in on it, making some units change role.
+        let frame = |x: f64, y: f64| {
+            state(
+                vec![
+                    vec![
+                        ship(0, 40.0, 40.0),
+                        ship(1, 42.0, 40.0),
+                        ship(2, 40.0, 42.0),
+                        ship(3, 42.0, 42.0),
+                        docked(ship(4, 30.0, 50.0), 0),
+                    ],
+                    vec![ship(10, x, y)],
+                ],
+                vec![
+                    owned(planet(0, 30.0, 58.0, 5.0), 0, &[4]),
+                    Planet { num_docking_spots: 2, ..planet(1, 160.0, 160.0, 6.0) },
+                ],
+            )
+        };
+        let (far, near) = (frame(180.0, 20.0), frame(45.0, 60.0));
+        let frames = [far.clone(), far, near.clone(), near];
+
+        let first = replay(&frames, Some(7));
+        assert_eq!(3, first.len());
+        assert!(first.iter().all(|commands| !commands.is_empty()));
+        assert_eq!(first, replay(&frames, Some(7)));
+        // Without a seed both runs derive the same one from the map.
+        assert_eq!(replay(&frames, None), replay(&frames, None));
+    }
+
+    #[test]
+    fn test_fleet_events() {
+        // Ship 1 is destroyed and ship 2 spawns while ship 0 docks at planet 0.
+        let planet = planet(0, 46.0, 50.0, 5.0);
+        let docking = Ship {
+            docking_status: DockingStatus::DOCKING,
+            ..docked(ship(0, 40.0, 50.0), 0)
+        };
+        let frames = [
+            state(vec![vec![ship(0, 40.0, 50.0), ship(1, 60.0, 60.0)]], vec![planet.clone()]),
+            state(vec![vec![docking, ship(1, 60.0, 60.0)]], vec![owned(planet.clone(), 0, &[0])]),
+            state(
+                vec![vec![docked(ship(0, 40.0, 50.0), 0), ship(2, 20.0, 20.0)]],
+                vec![owned(planet, 0, &[0])],
+            ),
+        ];
+        let mut game = game(200, 200, &frames);
+        let mut bot = Bot::new(&game, Some(1));
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let log = seen.clone();
+        bot.events.subscribe(Box::new(move |event| log.borrow_mut().push(*event)));
+        bot.initialize(&game.update_map().unwrap());
+
+        let mut budget = TurnBudget::new(Duration::from_secs(60));
+        bot.play_round(&game.update_map().unwrap(), &mut CommandQueue::new(), &mut budget);
+        assert_eq!(vec![FleetEvent::Spawned(0), FleetEvent::Spawned(1)], *seen.borrow());
+
+        seen.borrow_mut().clear();
+        bot.play_round(&game.update_map().unwrap(), &mut CommandQueue::new(), &mut budget);
+        assert_eq!(
+            vec![
+                FleetEvent::Destroyed(1),
+                FleetEvent::Docked { ship: 0, planet: 0 },
+                FleetEvent::Spawned(2),
+            ],
+            *seen.borrow()
+        );
+        let mut ids: Vec<i32> = bot.fleet.keys().cloned().collect();
+        ids.sort();
+        assert_eq!(vec![0, 2], ids);
+    }
+
+    #[test]
+    fn test_pressure() {
+        // Ships 0 and 1 head for planet 0. Before the critical turn ship 1
+        // ends up right in front of ship 0.
+        let frame = |second: Ship| {
+            state(
+                vec![vec![ship(0, 40.0, 50.0), second], vec![ship(5, 180.0, 180.0)]],
+                vec![planet(0, 80.0, 50.0, 5.0)],
+            )
+        };
+        let (apart, ahead) = (frame(ship(1, 40.0, 70.0)), frame(ship(1, 44.0, 50.0)));
+        let mut game = game(200, 200, &[apart.clone(), apart.clone(), apart, ahead.clone(), ahead]);
+        let mut bot = Bot::new(&game, Some(1));
+        bot.initialize(&game.update_map().unwrap());
+
+        let mut turns = Vec::new();
+        for &pressure in &[Pressure::Normal, Pressure::Reduced, Pressure::Critical, Pressure::Exhausted] {
+            let mut queue = CommandQueue::new();
+            bot.play_round(&game.update_map().unwrap(), &mut queue, &mut TurnBudget::fixed(pressure));
+            turns.push(queue.commands().to_vec());
+        }
+
+        let thrusts = vec![Command::Thrust(0, 7, 0), Command::Thrust(1, 7, 333)];
+        assert_eq!(thrusts, turns[0]);
+        // Cheaper navigation still finds the way.
+        assert_eq!(thrusts, turns[1]);
+        // Ship 1 repeats its last command, ship 0 would run into it and waits.
+        assert_eq!(vec![Command::Nop, Command::Thrust(1, 7, 333)], turns[2]);
+        assert!(turns[3].is_empty());
     }
 }
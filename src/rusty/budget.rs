@@ -0,0 +1,107 @@
+use std::time::{Duration, Instant};
+
+use super::constants::{CRITICAL_BUDGET, EXHAUSTED_BUDGET, REDUCED_BUDGET};
+
+/// How close the current turn is to running out of time.
+#[derive(PartialEq, PartialOrd, Debug, Clone, Copy)]
+pub enum Pressure {
+    /// Plenty of time, do everything properly.
+    Normal,
+    /// Use cheaper navigation.
+    Reduced,
+    /// Reuse last turn's command where possible.
+    Critical,
+    /// Out of time, remaining units get no command.
+    Exhausted,
+}
+
+/// Tracks how much of the engine's per turn time limit has been used.
+#[derive(Debug)]
+pub struct TurnBudget {
+    start: Instant,
+    limit: Duration,
+    checkpoint: Instant,
+    /// Pressure reported regardless of the clock.
+    fixed: Option<Pressure>,
+}
+
+impl TurnBudget {
+    /// Start the clock for a turn that must finish within `limit`.
+    pub fn new(limit: Duration) -> Self {
+        let now = Instant::now();
+        TurnBudget {
+            start: now,
+            limit,
+            checkpoint: now,
+            fixed: None,
+        }
+    }
+
+    /// A budget that always reports `pressure`.
+    #[cfg(test)]
+    pub fn fixed(pressure: Pressure) -> Self {
+        TurnBudget {
+            fixed: Some(pressure),
+            ..TurnBudget::new(Duration::from_secs(0))
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Fraction of the time limit used so far.
+    pub fn used(&self) -> f64 {
+        let limit = duration_millis(self.limit);
+        if limit <= 0.0 {
+            return 1.0;
+        }
+        duration_millis(self.elapsed()) / limit
+    }
+
+    pub fn pressure(&self) -> Pressure {
+        if let Some(pressure) = self.fixed {
+            return pressure;
+        }
+        let used = self.used();
+        if used >= EXHAUSTED_BUDGET {
+            Pressure::Exhausted
+        } else if used >= CRITICAL_BUDGET {
+            Pressure::Critical
+        } else if used >= REDUCED_BUDGET {
+            Pressure::Reduced
+        } else {
+            Pressure::Normal
+        }
+    }
+
+    /// Log the time spent since the previous checkpoint as `phase`.
+    pub fn checkpoint(&mut self, phase: &str) {
+        let now = Instant::now();
+        let spent = duration_millis(now - self.checkpoint);
+        self.checkpoint = now;
+        info!(
+            "Phase {} took {:.1}ms, {:.0}% of the turn used",
+            phase,
+            spent,
+            self.used() * 100.0
+        );
+    }
+}
+
+fn duration_millis(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1000.0 + duration.subsec_nanos() as f64 / 1_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use super::{Pressure, TurnBudget};
+
+    #[test]
+    fn test_pressure() {
+        assert_eq!(Pressure::Normal, TurnBudget::new(Duration::from_secs(3600)).pressure());
+        assert_eq!(Pressure::Exhausted, TurnBudget::new(Duration::from_secs(0)).pressure());
+        assert_eq!(Pressure::Critical, TurnBudget::fixed(Pressure::Critical).pressure());
+    }
+}
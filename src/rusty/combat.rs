@@ -0,0 +1,151 @@
+use hlt::constants::{MAX_SPEED, WEAPON_RADIUS};
+use hlt::entity::{DockingStatus, Entity, Position, Ship};
+use hlt::simulator::process_attacks;
+
+use super::constants::{ATTACK_MARGIN, COMBAT_TURNS, RETREAT_LOSS_RATIO};
+
+/// What a unit should do about a fight it could get into.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Engagement {
+    /// We come out ahead, close in.
+    Engage,
+    /// Too close to call, stay out of reach until help arrives.
+    Wait,
+    /// We would be beaten, get away.
+    Retreat,
+}
+
+/// The ships of both sides left standing after a fight.
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub ours: Vec<Ship>,
+    pub theirs: Vec<Ship>,
+}
+
+/// Play out `turns` turns of a fight in which every undocked ship flies at
+/// the closest enemy at full speed until it is in weapon range, then
+/// everyone fires as the engine resolves it, splitting `WEAPON_DAMAGE`
+/// among all enemies in range.
+pub fn simulate(ours: &[Ship], theirs: &[Ship], turns: i32) -> Outcome {
+    let mut ships: Vec<(usize, Ship)> = ours
+        .iter()
+        .map(|ship| (0, ship.clone()))
+        .chain(theirs.iter().map(|ship| (1, ship.clone())))
+        .collect();
+
+    for _ in 0..turns {
+        for &mut (_, ref mut ship) in ships.iter_mut() {
+            ship.cooldown = (ship.cooldown - 1).max(0);
+        }
+
+        let moves: Vec<Position> = ships
+            .iter()
+            .map(|&(side, ref ship)| {
+                let enemy = ships
+                    .iter()
+                    .filter(|&&(other_side, _)| other_side != side)
+                    .map(|(_, other)| other)
+                    .min_by(|a, b| ship.distance_with(*a).total_cmp(&ship.distance_with(*b)));
+                match enemy {
+                    Some(enemy) if ship.docking_status == DockingStatus::UNDOCKED => {
+                        approach(ship, enemy)
+                    }
+                    _ => ship.position,
+                }
+            })
+            .collect();
+        for (&mut (_, ref mut ship), pos) in ships.iter_mut().zip(moves) {
+            ship.position = pos;
+        }
+
+        process_attacks(&mut ships);
+        ships.retain(|(_, ship)| ship.hp > 0);
+    }
+
+    let mut outcome = Outcome { ours: Vec::new(), theirs: Vec::new() };
+    for (side, ship) in ships {
+        if side == 0 {
+            outcome.ours.push(ship);
+        } else {
+            outcome.theirs.push(ship);
+        }
+    }
+    outcome
+}
+
+/// Where a ship ends up this turn heading into weapon range of `enemy`.
+fn approach(ship: &Ship, enemy: &Ship) -> Position {
+    let distance = ship.distance_with(enemy) - (WEAPON_RADIUS - ATTACK_MARGIN);
+    if distance <= 0.0 {
+        return ship.position;
+    }
+    let angle = ship.angle_with(enemy).to_radians();
+    Position::from_origin(ship, angle, distance.min(MAX_SPEED as f64))
+}
+
+pub fn total_hp(ships: &[Ship]) -> i32 {
+    ships.iter().map(|ship| ship.hp).sum()
+}
+
+/// Engage when the enemy loses at least as much health as we do over
+/// `COMBAT_TURNS`, retreat when we are wiped out or lose clearly more.
+pub fn engagement(ours: &[Ship], theirs: &[Ship]) -> Engagement {
+    let outcome = simulate(ours, theirs, COMBAT_TURNS);
+    let our_loss = total_hp(ours) - total_hp(&outcome.ours);
+    let their_loss = total_hp(theirs) - total_hp(&outcome.theirs);
+
+    if outcome.theirs.is_empty() && !outcome.ours.is_empty() || their_loss >= our_loss {
+        Engagement::Engage
+    } else if outcome.ours.is_empty() || our_loss as f64 > RETREAT_LOSS_RATIO * their_loss as f64 {
+        Engagement::Retreat
+    } else {
+        Engagement::Wait
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hlt::constants::{MAX_SHIP_HEALTH, WEAPON_DAMAGE};
+    use hlt::entity::DockingStatus;
+    use hlt::fixture::ship;
+    use super::{engagement, simulate, total_hp, Engagement};
+
+    #[test]
+    fn test_simulate() {
+        // Two of ours against one 10 apart: both close in and fire on the
+        // first turn, the lone enemy splits its damage.
+        let ours = [ship(0, 0.0, 0.0), ship(1, 0.0, 1.0)];
+        let theirs = [ship(2, 10.0, 0.0)];
+        let outcome = simulate(&ours, &theirs, 1);
+        let hp: Vec<i32> = outcome.ours.iter().map(|ship| ship.hp).collect();
+        assert_eq!(vec![MAX_SHIP_HEALTH - WEAPON_DAMAGE / 2; 2], hp);
+        assert_eq!(MAX_SHIP_HEALTH - 2 * WEAPON_DAMAGE, total_hp(&outcome.theirs));
+
+        // Out of reach for a turn, nobody gets hurt.
+        let far = [ship(2, 40.0, 0.0)];
+        assert_eq!(MAX_SHIP_HEALTH, total_hp(&simulate(&ours, &far, 1).theirs));
+
+        // The lone enemy goes down on the second turn.
+        assert!(simulate(&ours, &theirs, 2).theirs.is_empty());
+    }
+
+    #[test]
+    fn test_engagement() {
+        let us = [ship(0, 0.0, 0.0)];
+        assert_eq!(Engagement::Engage, engagement(&us, &[ship(1, 10.0, 0.0)]));
+        let group = [ship(1, 10.0, 0.0), ship(2, 10.0, 1.0), ship(3, 10.0, -1.0)];
+        assert_eq!(Engagement::Retreat, engagement(&us, &group));
+
+        // A docked enemy can't fire back.
+        let mut docked = ship(1, 3.0, 0.0);
+        docked.docking_status = DockingStatus::DOCKED;
+        assert_eq!(Engagement::Engage, engagement(&us, &[docked]));
+
+        // Even numbers but one of ours can't fire yet: we lose a bit more
+        // than them and had better wait for it.
+        let mut cooling = ship(1, 0.0, 1.0);
+        cooling.cooldown = 2;
+        let pair = [ship(0, 0.0, 0.0), cooling];
+        assert_eq!(Engagement::Wait, engagement(&pair, &[ship(2, 3.0, 0.0), ship(3, 3.0, 1.0)]));
+    }
+}
@@ -4,3 +4,125 @@ pub const MIN_PLANET_DISTANCE: f64 = 3.0;
 
 // Minimum distance to a ship
 pub const MIN_SHIP_DISTANCE: f64 = 0.5;
+
+// Time the engine allows for a single turn in milliseconds
+pub const TURN_TIME_LIMIT: u64 = 2000;
+
+// Fraction of the turn after which navigation is made cheaper
+pub const REDUCED_BUDGET: f64 = 0.5;
+
+// Fraction of the turn after which units reuse last turn's command
+pub const CRITICAL_BUDGET: f64 = 0.7;
+
+// Fraction of the turn after which the remaining units are skipped
+pub const EXHAUSTED_BUDGET: f64 = 0.85;
+
+// Course corrections tried when navigating, half at full and half at half speed, each
+// turning alternately left and right of the target by one more angular step
+pub const NAVIGATION_ATTEMPTS: i32 = 4 * 50;
+
+// Course corrections tried when navigating short on time, split the same way
+pub const REDUCED_NAVIGATION_ATTEMPTS: i32 = 4 * 5;
+
+// Largest gap between planet surfaces for them to share a cluster
+pub const CLUSTER_DISTANCE: f64 = 10.0;
+
+// Distance from the line between two spawns at which a planet is a chokepoint
+pub const CHOKEPOINT_DISTANCE: f64 = 5.0;
+
+// Distance a docking spot is worth when ranking planets to expand to
+pub const EXPANSION_SPOT_VALUE: f64 = 5.0;
+
+// How much further than the nearest one a settler target may be and still
+// count as a tie, ties are ranked by cluster and the map analysis expansion
+// order
+pub const EXPANSION_TIE_DISTANCE: f64 = 7.0;
+
+// Corners of the polygon the pathfinder places around each planet
+pub const PATH_NODES_PER_PLANET: usize = 8;
+
+// How much closer than MIN_PLANET_DISTANCE a path may pass a planet
+pub const PATH_TOLERANCE: f64 = 0.5;
+
+// Distance at which a waypoint counts as reached
+pub const WAYPOINT_RADIUS: f64 = 1.0;
+
+// Distance the destination may move before the path is planned again
+pub const REPLAN_DISTANCE: f64 = 2.0;
+
+// Distance an enemy being docked is worth when choosing what to attack
+pub const DOCKED_TARGET_BONUS: f64 = 20.0;
+
+// Distance added to an attack target for each armed enemy taking part in a
+// fight over it we wouldn't win
+pub const GUARDED_TARGET_PENALTY: f64 = 10.0;
+
+// How far inside weapon range attackers close in, and how far beyond the
+// enemies' reach they wait
+pub const ATTACK_MARGIN: f64 = 1.0;
+
+// Distance from the surface of our planets at which enemies are intercepted
+pub const DEFENSE_RADIUS: f64 = 30.0;
+
+// Distance from the surface of a planet at which defenders wait
+pub const DEFENSE_HOLD_DISTANCE: f64 = 5.0;
+
+// Most ships a saboteur expects to crash into a planet to destroy it
+pub const SABOTAGE_MAX_CRASHES: i32 = 2;
+
+// Hit points a unit of distance to a sabotage target costs
+pub const SABOTAGE_DISTANCE_COST: f64 = 2.0;
+
+// Largest fraction of the fleet sent to defend our planets
+pub const MAX_DEFENSE_SHARE: f64 = 0.3;
+
+// Turn from which a fleet behind in production starts sabotaging
+pub const SABOTAGE_START_TURN: i32 = 100;
+
+// Share of all docked ships below which the fleet counts as behind
+pub const SABOTAGE_SHARE: f64 = 0.4;
+
+// Most units sabotaging at once
+pub const MAX_SABOTEURS: usize = 2;
+
+// Turns of enemy positions remembered
+pub const TRACK_HISTORY: usize = 5;
+
+// Turns of movement averaged to estimate an enemy's velocity
+pub const TRACK_SMOOTHING: usize = 2;
+
+// Speed below which an enemy counts as standing still
+pub const MIN_TRACK_SPEED: f64 = 0.5;
+
+// How far ahead of an enemy its target is looked for
+pub const INTENT_RANGE: f64 = 50.0;
+
+// How far off an enemy's course its target may lie
+pub const INTENT_TOLERANCE: f64 = 2.0;
+
+// Side length of a cell of the influence map
+pub const INFLUENCE_CELL_SIZE: f64 = 4.0;
+
+// Distance beyond its reach over which a ship's influence fades out
+pub const INFLUENCE_FALLOFF: f64 = 7.0;
+
+// Fraction of its firepower a ship whose weapon cools down counts for
+pub const COOLDOWN_INFLUENCE: f64 = 0.5;
+
+// Extra cost of a unit of distance for each unit of enemy firepower on the way
+pub const THREAT_PATH_COST: f64 = 10.0;
+
+// Distance a unit of enemy firepower around a planet is worth when expanding
+pub const EXPANSION_THREAT_COST: f64 = 10.0;
+
+// Turns of fighting simulated before engaging
+pub const COMBAT_TURNS: i32 = 3;
+
+// Distance from an enemy within which ships take part in a fight with it
+pub const COMBAT_RADIUS: f64 = 19.0;
+
+// How many times the health the enemy loses we may lose before retreating
+pub const RETREAT_LOSS_RATIO: f64 = 1.5;
+
+// Directions considered when falling back from a fight
+pub const RETREAT_DIRECTIONS: usize = 8;
@@ -0,0 +1,102 @@
+use std::fmt::{Debug, Display, Formatter, Result};
+
+/// Something that happened to one of our ships since the previous turn.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum FleetEvent {
+    /// A new ship joined the fleet.
+    Spawned(i32),
+    /// The ship is gone, its unit has been removed.
+    Destroyed(i32),
+    /// The ship finished docking at the planet.
+    Docked { ship: i32, planet: i32 },
+    /// The ship finished undocking.
+    Undocked(i32),
+    /// The ship lost its claim on a docking spot of the planet.
+    TargetLost { ship: i32, planet: i32 },
+}
+
+impl FleetEvent {
+    /// The ship the event is about.
+    pub fn ship_id(&self) -> i32 {
+        match *self {
+            FleetEvent::Spawned(ship) |
+            FleetEvent::Destroyed(ship) |
+            FleetEvent::Docked { ship, .. } |
+            FleetEvent::Undocked(ship) |
+            FleetEvent::TargetLost { ship, .. } => ship,
+        }
+    }
+}
+
+impl Display for FleetEvent {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match *self {
+            FleetEvent::Spawned(ship) => write!(f, "ship {} spawned", ship),
+            FleetEvent::Destroyed(ship) => write!(f, "ship {} destroyed", ship),
+            FleetEvent::Docked { ship, planet } => write!(f, "ship {} docked at {}", ship, planet),
+            FleetEvent::Undocked(ship) => write!(f, "ship {} undocked", ship),
+            FleetEvent::TargetLost { ship, planet } => {
+                write!(f, "ship {} lost its spot on {}", ship, planet)
+            }
+        }
+    }
+}
+
+/// Called with every event.
+pub type Listener = Box<dyn FnMut(&FleetEvent)>;
+
+/// Hands fleet events to whoever subscribed, in order of subscription.
+#[derive(Default)]
+pub struct EventBus {
+    listeners: Vec<Listener>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        EventBus { listeners: Vec::new() }
+    }
+
+    pub fn subscribe(&mut self, listener: Listener) {
+        self.listeners.push(listener);
+    }
+
+    pub fn publish(&mut self, event: &FleetEvent) {
+        for listener in &mut self.listeners {
+            listener(event);
+        }
+    }
+}
+
+impl Debug for EventBus {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "EventBus {{ listeners: {} }}", self.listeners.len())
+    }
+}
+
+/// Listener writing every event to the log.
+pub fn log_event(event: &FleetEvent) {
+    info!("Fleet event: {}", event);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use super::{EventBus, FleetEvent};
+
+    #[test]
+    fn test_event_bus() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let mut bus = EventBus::new();
+        let log = seen.clone();
+        bus.subscribe(Box::new(move |event| log.borrow_mut().push(*event)));
+
+        bus.publish(&FleetEvent::Spawned(3));
+        bus.publish(&FleetEvent::Docked { ship: 3, planet: 1 });
+        assert_eq!(
+            vec![FleetEvent::Spawned(3), FleetEvent::Docked { ship: 3, planet: 1 }],
+            *seen.borrow()
+        );
+        assert_eq!(3, seen.borrow()[1].ship_id());
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::BinaryHeap;
+use std::f64;
+
+use hlt::constants::{MAX_SHIP_HEALTH, MAX_SPEED, WEAPON_RADIUS};
+use hlt::entity::{DockingStatus, Entity, Position, Ship};
+use hlt::game_map::GameMap;
+
+use super::constants::{COOLDOWN_INFLUENCE, INFLUENCE_CELL_SIZE, INFLUENCE_FALLOFF, THREAT_PATH_COST};
+use super::search::Node;
+
+/// Firepower of our ships and of the enemies' spread over a grid covering
+/// the map. A ship counts fully everywhere it can shoot at next turn and
+/// fades out over `INFLUENCE_FALLOFF` beyond that.
+#[derive(Debug, Default)]
+pub struct InfluenceMap {
+    columns: usize,
+    rows: usize,
+    friendly: Vec<f64>,
+    enemy: Vec<f64>,
+    /// Cells covered by a planet.
+    blocked: Vec<bool>,
+}
+
+impl InfluenceMap {
+    pub fn new(game_map: &GameMap) -> Self {
+        let (width, height) = game_map.size();
+        let columns = (width as f64 / INFLUENCE_CELL_SIZE).ceil().max(1.0) as usize;
+        let rows = (height as f64 / INFLUENCE_CELL_SIZE).ceil().max(1.0) as usize;
+        let mut map = InfluenceMap {
+            columns,
+            rows,
+            friendly: vec![0.0; columns * rows],
+            enemy: vec![0.0; columns * rows],
+            blocked: vec![false; columns * rows],
+        };
+
+        let me = game_map.me().id;
+        for player in game_map.all_players() {
+            for ship in player.all_ships() {
+                map.spread(ship, player.id == me);
+            }
+        }
+        for planet in game_map.all_planets().iter().filter(|planet| !planet.is_dead()) {
+            for index in map.cells_within(planet, planet.radius) {
+                if planet.distance_with(&map.center(index)) <= planet.radius {
+                    map.blocked[index] = true;
+                }
+            }
+        }
+        map
+    }
+
+    /// Enemy firepower able to reach `pos`.
+    pub fn threat_at<T: Entity>(&self, pos: &T) -> f64 {
+        self.cell(pos).map_or(0.0, |index| self.enemy[index])
+    }
+
+    /// Our firepower able to reach `pos`.
+    pub fn support_at<T: Entity>(&self, pos: &T) -> f64 {
+        self.cell(pos).map_or(0.0, |index| self.friendly[index])
+    }
+
+    /// Path from `start` to `goal` trading distance against threat, as the
+    /// waypoints after `start` ending with `goal`. Cells covered by planets
+    /// are avoided.
+    pub fn safest_path<E: Entity, T: Entity>(&self, start: &E, goal: &T) -> Option<Vec<Position>> {
+        let (from, to) = (self.cell(start)?, self.cell(goal)?);
+        let goal_center = self.center(to);
+
+        let mut cost = vec![f64::INFINITY; self.cells()];
+        let mut previous: Vec<Option<usize>> = vec![None; self.cells()];
+        let mut open = BinaryHeap::new();
+        cost[from] = 0.0;
+        open.push(Node { estimate: 0.0, index: from });
+
+        while let Some(Node { estimate, index }) = open.pop() {
+            if index == to {
+                let mut path = vec![goal.position()];
+                let mut current = to;
+                while let Some(before) = previous[current] {
+                    if before != from {
+                        path.push(self.center(before));
+                    }
+                    current = before;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if estimate > cost[index] + self.center(index).distance_with(&goal_center) {
+                continue;
+            }
+
+            for next in self.neighbours(index) {
+                if self.blocked[next] && next != to {
+                    continue;
+                }
+                let step = self.center(index).distance_with(&self.center(next));
+                let through = cost[index] + step * (1.0 + THREAT_PATH_COST * self.enemy[next]);
+                if through < cost[next] {
+                    cost[next] = through;
+                    previous[next] = Some(index);
+                    open.push(Node {
+                        estimate: through + self.center(next).distance_with(&goal_center),
+                        index: next,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    fn cells(&self) -> usize {
+        self.columns * self.rows
+    }
+
+    fn cell<T: Entity>(&self, pos: &T) -> Option<usize> {
+        let Position(x, y) = pos.position();
+        if x < 0.0 || y < 0.0 {
+            return None;
+        }
+        let (column, row) = ((x / INFLUENCE_CELL_SIZE) as usize, (y / INFLUENCE_CELL_SIZE) as usize);
+        if column < self.columns && row < self.rows {
+            Some(row * self.columns + column)
+        } else {
+            None
+        }
+    }
+
+    fn center(&self, index: usize) -> Position {
+        let (column, row) = (index % self.columns, index / self.columns);
+        Position(
+            (column as f64 + 0.5) * INFLUENCE_CELL_SIZE,
+            (row as f64 + 0.5) * INFLUENCE_CELL_SIZE,
+        )
+    }
+
+    /// Cells whose square lies at least partly within `radius` of `pos`.
+    fn cells_within<T: Entity>(&self, pos: &T, radius: f64) -> Vec<usize> {
+        let Position(x, y) = pos.position();
+        let span = |value: f64, count: usize| {
+            let low = ((value - radius) / INFLUENCE_CELL_SIZE).floor().max(0.0) as usize;
+            let high = ((value + radius) / INFLUENCE_CELL_SIZE).floor().max(0.0) as usize;
+            (low, high.min(count - 1))
+        };
+        let (first_column, last_column) = span(x, self.columns);
+        let (first_row, last_row) = span(y, self.rows);
+        let mut cells = Vec::new();
+        for row in first_row..(last_row + 1) {
+            for column in first_column..(last_column + 1) {
+                cells.push(row * self.columns + column);
+            }
+        }
+        cells
+    }
+
+    fn neighbours(&self, index: usize) -> Vec<usize> {
+        let (column, row) = ((index % self.columns) as i64, (index / self.columns) as i64);
+        let mut cells = Vec::new();
+        for dy in -1..2 {
+            for dx in -1..2 {
+                let (x, y) = (column + dx, row + dy);
+                if (dx, dy) != (0, 0) && x >= 0 && y >= 0 && x < self.columns as i64 && y < self.rows as i64 {
+                    cells.push(y as usize * self.columns + x as usize);
+                }
+            }
+        }
+        cells
+    }
+
+    fn spread(&mut self, ship: &Ship, friendly: bool) {
+        let strength = firepower(ship);
+        if strength == 0.0 {
+            return;
+        }
+        let reach = WEAPON_RADIUS + MAX_SPEED as f64;
+        for index in self.cells_within(ship, reach + INFLUENCE_FALLOFF) {
+            let distance = ship.distance_with(&self.center(index));
+            let weight = if distance <= reach {
+                1.0
+            } else {
+                (1.0 - (distance - reach) / INFLUENCE_FALLOFF).max(0.0)
+            };
+            let grid = if friendly { &mut self.friendly } else { &mut self.enemy };
+            grid[index] += strength * weight;
+        }
+    }
+}
+
+/// What a ship adds to the map: its health as a fraction of the full amount,
+/// less while its weapon cools down. Docked ships can't fire at all.
+fn firepower(ship: &Ship) -> f64 {
+    if ship.docking_status != DockingStatus::UNDOCKED {
+        return 0.0;
+    }
+    let health = ship.hp as f64 / MAX_SHIP_HEALTH as f64;
+    if ship.cooldown > 0 {
+        health * COOLDOWN_INFLUENCE
+    } else {
+        health
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hlt::entity::{Entity, Position, Ship};
+    use hlt::fixture::{docked, game_map, owned, planet, ship, state};
+    use rusty::constants::COOLDOWN_INFLUENCE;
+    use super::InfluenceMap;
+
+    #[test]
+    fn test_influence() {
+        // Our ship 0 at (10, 50), enemy ship 1 at full health at (50, 50),
+        // enemy ship 2 at half health cooling down at (50, 10) and docked
+        // enemy ship 3 at (90, 90).
+        let game_map = game_map(state(
+            vec![
+                vec![ship(0, 10.0, 50.0)],
+                vec![
+                    ship(1, 50.0, 50.0),
+                    Ship { hp: 127, cooldown: 1, ..ship(2, 50.0, 10.0) },
+                    docked(ship(3, 90.0, 90.0), 0),
+                ],
+            ],
+            vec![owned(planet(0, 30.0, 80.0, 5.0), 1, &[3])],
+        ));
+        let map = InfluenceMap::new(&game_map);
+
+        assert_eq!(1.0, map.threat_at(&Position(50.0, 50.0)));
+        assert_eq!(0.0, map.threat_at(&Position(10.0, 50.0)));
+        assert_eq!(1.0, map.support_at(&Position(10.0, 50.0)));
+        assert!((map.threat_at(&Position(50.0, 10.0)) - 0.5 * COOLDOWN_INFLUENCE).abs() < 0.01);
+        assert_eq!(0.0, map.threat_at(&Position(90.0, 90.0)));
+        assert_eq!(0.0, map.threat_at(&Position(-5.0, 50.0)));
+
+        // Flying past ship 1 the path stays out of its reach, and clear of
+        // planet 0.
+        let path = map.safest_path(&Position(10.0, 50.0), &Position(90.0, 50.0)).unwrap();
+        assert_eq!(Position(90.0, 50.0), *path.last().unwrap());
+        assert!(path.iter().all(|pos| map.threat_at(pos) < 1.0));
+        assert!(path.iter().all(|pos| pos.distance_with(&Position(30.0, 80.0)) > 5.0));
+    }
+}
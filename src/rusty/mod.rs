@@ -2,5 +2,17 @@ pub mod bot;
 pub mod behavior;
 pub mod unit;
 pub mod constants;
+pub mod budget;
+pub mod analysis;
+pub mod pathfinding;
+pub mod allocator;
+pub mod random;
+pub mod reservation;
+pub mod events;
+pub mod tracker;
+pub mod influence;
+pub mod combat;
+pub mod offline;
+mod search;
 
 pub use self::bot::Bot;
@@ -0,0 +1,155 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::{self, BufReader, Read, Write};
+use std::rc::Rc;
+use std::time::Duration;
+
+use hlt::command::CommandQueue;
+use hlt::entity::GameState;
+use hlt::game::{Game, GameError};
+use hlt::game_map::GameMap;
+use hlt::simulator::Simulator;
+use rusty::Bot;
+use rusty::budget::TurnBudget;
+use rusty::constants::TURN_TIME_LIMIT;
+
+/// In-memory stream standing in for the engine's end of stdin or stdout.
+#[derive(Clone, Default)]
+struct Pipe(Rc<RefCell<VecDeque<u8>>>);
+
+impl Pipe {
+    fn push(&self, text: &str) {
+        self.0.borrow_mut().extend(text.bytes());
+    }
+
+    /// Everything written since the last call, without the trailing newline.
+    fn take_line(&self) -> String {
+        let bytes: Vec<u8> = self.0.borrow_mut().drain(..).collect();
+        String::from_utf8_lossy(&bytes).trim_end().to_string()
+    }
+}
+
+impl Read for Pipe {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.borrow_mut().read(buf)
+    }
+}
+
+impl Write for Pipe {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A bot connected to the simulator instead of the engine. It goes through
+/// the same handshake, frame parsing and command encoding as `main`.
+struct Seat {
+    game: Game<BufReader<Pipe>, Pipe>,
+    bot: Bot,
+    input: Pipe,
+    output: Pipe,
+    command_queue: CommandQueue,
+}
+
+impl Seat {
+    fn new(
+        id: usize,
+        simulator: &Simulator,
+        frame: &str,
+        seed: Option<u64>,
+    ) -> Result<Seat, GameError> {
+        let input = Pipe::default();
+        let output = Pipe::default();
+        input.push(&format!("{}\n{} {}\n", id, simulator.map_width, simulator.map_height));
+        input.push(frame);
+
+        let mut game = Game::with_transport(BufReader::new(input.clone()), output.clone())?;
+        let mut bot = Bot::new(&game, seed);
+        let game_map = game.update_map()?;
+        bot.initialize(&game_map);
+        game.send_ready(&bot.name)?;
+        output.take_line();
+
+        Ok(Seat {
+            game,
+            bot,
+            input,
+            output,
+            command_queue: CommandQueue::new(),
+        })
+    }
+
+    /// Play one turn from the frame and return the line of commands sent.
+    fn play(&mut self, frame: &str) -> String {
+        self.input.push(frame);
+        match self.game.update_map() {
+            Ok(game_map) => self.play_round(&game_map),
+            Err(err) => error!("Skipping turn, unable to read map: {}", err),
+        }
+
+        if let Err(err) = self.game.send_command_queue(self.command_queue.commands()) {
+            error!("Unable to send commands: {}", err);
+        }
+        self.command_queue.clear();
+        self.output.take_line()
+    }
+
+    fn play_round(&mut self, game_map: &GameMap) {
+        let mut budget = TurnBudget::new(Duration::from_millis(TURN_TIME_LIMIT));
+        self.bot.play_round(game_map, &mut self.command_queue, &mut budget);
+        for err in self.command_queue.validate(game_map) {
+            warn!("Dropping invalid command: {}", err);
+        }
+    }
+}
+
+/// Turn limit of the engine for a map of this size.
+fn max_turns(simulator: &Simulator) -> usize {
+    100 + f64::from(simulator.map_width * simulator.map_height).sqrt() as usize
+}
+
+/// Play a game of the bot against itself on a map given as its width and
+/// height followed by the first frame, one seat per player in the frame.
+/// Returns the state the game ended in.
+pub fn play(input: &str, seed: Option<u64>) -> Result<GameState, GameError> {
+    let (mut simulator, state) = Simulator::load(input)?;
+    let frame = Simulator::frame(&state);
+    let mut seats = Vec::new();
+    for id in 0..state.players.len() {
+        seats.push(Seat::new(id, &simulator, &frame, seed)?);
+    }
+
+    let turns = max_turns(&simulator);
+    let mut bots: Vec<_> = seats
+        .iter_mut()
+        .map(|seat| move |frame: &str| seat.play(frame))
+        .collect();
+    Ok(simulator.play(state, &mut bots, turns))
+}
+
+#[cfg(test)]
+mod tests {
+    use hlt::fixture::{ship, state};
+    use hlt::simulator::Simulator;
+    use super::play;
+
+    #[test]
+    fn test_play_to_winner() {
+        let start = state(
+            vec![
+                vec![ship(0, 10.0, 10.0), ship(1, 10.0, 12.0), ship(2, 10.0, 14.0)],
+                vec![ship(3, 50.0, 50.0)],
+            ],
+            Vec::new(),
+        );
+        let input = format!("60 60\n{}", Simulator::frame(&start));
+
+        let end = play(&input, Some(7)).unwrap();
+        assert_eq!(vec![0], Simulator::living_players(&end));
+    }
+}
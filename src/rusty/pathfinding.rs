@@ -0,0 +1,200 @@
+use std::collections::{BinaryHeap, VecDeque};
+use std::f64;
+use std::f64::consts::PI;
+
+use hlt::entity::{Entity, Planet, Position, Ship};
+use hlt::game_map::GameMap;
+
+use super::constants::{MIN_PLANET_DISTANCE, PATH_NODES_PER_PLANET, PATH_TOLERANCE, REPLAN_DISTANCE,
+                       WAYPOINT_RADIUS};
+use super::search::Node;
+
+/// The waypoints a unit follows towards its destination over several turns.
+#[derive(Debug, Default)]
+pub struct Route {
+    waypoints: VecDeque<Position>,
+}
+
+impl Route {
+    pub fn new() -> Self {
+        Route { waypoints: VecDeque::new() }
+    }
+
+    pub fn clear(&mut self) {
+        self.waypoints.clear()
+    }
+
+    /// Follow the path to `goal` planned on earlier turns, planning it again
+    /// when the goal moved or the next leg got blocked.
+    pub fn next_waypoint(&mut self, ship: &Ship, goal: Position, game_map: &GameMap) -> Position {
+        let here = ship.position();
+        while self.waypoints.len() > 1 &&
+            self.waypoints.front().is_some_and(|w| here.distance_with(w) < WAYPOINT_RADIUS)
+        {
+            self.waypoints.pop_front();
+        }
+
+        let stale = self.waypoints
+            .back()
+            .is_none_or(|last| last.distance_with(&goal) > REPLAN_DISTANCE);
+        let blocked = self.waypoints
+            .front()
+            .is_none_or(|next| !is_clear(game_map, &here, next));
+        if stale || blocked {
+            let path = find_path(game_map, &here, &goal).unwrap_or_else(|| vec![goal]);
+            self.waypoints = path.into_iter().collect();
+            debug!("Ship {}: route planned {:?}", ship.id, self.waypoints);
+        }
+
+        // Cut corners whenever a later waypoint is already in sight
+        while self.waypoints.len() > 1 && is_clear(game_map, &here, &self.waypoints[1]) {
+            self.waypoints.pop_front();
+        }
+        self.waypoints.front().cloned().unwrap_or(goal)
+    }
+}
+
+/// Shortest path from `start` to `goal` that keeps `MIN_PLANET_DISTANCE` away
+/// from every planet, as the waypoints to visit after `start` ending with
+/// `goal`. Searches a visibility graph over points circling each planet.
+pub fn find_path<E: Entity, T: Entity>(game_map: &GameMap, start: &E, goal: &T) -> Option<Vec<Position>> {
+    let (start, goal) = (start.position(), goal.position());
+    if is_clear(game_map, &start, &goal) {
+        return Some(vec![goal]);
+    }
+
+    let mut nodes = vec![start, goal];
+    for planet in game_map.all_planets().iter().filter(|planet| !planet.is_dead()) {
+        nodes.extend(
+            ring(planet)
+                .into_iter()
+                .filter(|node| game_map.contains(node) && inside(game_map, node).is_empty()),
+        );
+    }
+
+    let mut cost = vec![f64::INFINITY; nodes.len()];
+    let mut previous: Vec<Option<usize>> = vec![None; nodes.len()];
+    let mut closed = vec![false; nodes.len()];
+    let mut open = BinaryHeap::new();
+    cost[0] = 0.0;
+    open.push(Node { estimate: start.distance_with(&goal), index: 0 });
+
+    while let Some(Node { index, .. }) = open.pop() {
+        if index == 1 {
+            let mut path = vec![goal];
+            let mut current = 1;
+            while let Some(before) = previous[current] {
+                if before != 0 {
+                    path.push(nodes[before]);
+                }
+                current = before;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        if closed[index] {
+            continue;
+        }
+        closed[index] = true;
+
+        for next in 1..nodes.len() {
+            if closed[next] || next == index {
+                continue;
+            }
+            let through = cost[index] + nodes[index].distance_with(&nodes[next]);
+            if through < cost[next] && is_clear(game_map, &nodes[index], &nodes[next]) {
+                cost[next] = through;
+                previous[next] = Some(index);
+                open.push(Node {
+                    estimate: through + nodes[next].distance_with(&goal),
+                    index: next,
+                });
+            }
+        }
+    }
+    None
+}
+
+/// Test whether the segment between two points keeps clear of planets. A
+/// planet an endpoint is already too close to, such as the one a ship
+/// spawned next to or is about to dock with, only counts if the segment
+/// gets any closer to it than that endpoint.
+pub fn is_clear(game_map: &GameMap, from: &Position, to: &Position) -> bool {
+    let (near_from, near_to) = (inside(game_map, from), inside(game_map, to));
+    game_map
+        .planets_along(from, to, MIN_PLANET_DISTANCE - PATH_TOLERANCE)
+        .iter()
+        .all(|planet| {
+            near_from.contains(&planet.id) && moves_away(planet, from, to) ||
+                near_to.contains(&planet.id) && moves_away(planet, to, from)
+        })
+}
+
+/// Test whether going from `from` towards `to` starts out moving away from
+/// the planet. The distance to its center along a straight line has a
+/// single minimum, so the segment then never gets closer than `from`.
+fn moves_away(planet: &Planet, from: &Position, to: &Position) -> bool {
+    let (Position(x, y), Position(to_x, to_y)) = (*from, *to);
+    let Position(center_x, center_y) = planet.position();
+    (to_x - x) * (x - center_x) + (to_y - y) * (y - center_y) >= 0.0
+}
+
+/// Ids of the planets closer to `pos` than the path clearance.
+fn inside(game_map: &GameMap, pos: &Position) -> Vec<i32> {
+    game_map
+        .planets_within(pos, MIN_PLANET_DISTANCE - PATH_TOLERANCE)
+        .iter()
+        .map(|planet| planet.id)
+        .collect()
+}
+
+/// Corners of a polygon around the planet whose sides stay
+/// `MIN_PLANET_DISTANCE` from its surface.
+fn ring(planet: &Planet) -> Vec<Position> {
+    let step = 2.0 * PI / PATH_NODES_PER_PLANET as f64;
+    let radius = (planet.radius + MIN_PLANET_DISTANCE) / f64::cos(step / 2.0);
+    (0..PATH_NODES_PER_PLANET)
+        .map(|i| Position::from_origin(planet, step * i as f64, radius))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use hlt::entity::{Entity, Position};
+    use hlt::fixture::{game_map, planet, ship, state};
+    use rusty::constants::MIN_PLANET_DISTANCE;
+    use super::{find_path, is_clear};
+
+    #[test]
+    fn test_find_path() {
+        // A planet of radius 10 at (50, 50) sits between (20, 50) and (80, 50).
+        let game_map = game_map(state(vec![vec![ship(0, 20.0, 50.0)]], vec![planet(0, 50.0, 50.0, 10.0)]));
+        let planet = game_map.get_planet(0).unwrap();
+        let (start, goal) = (Position(20.0, 50.0), Position(80.0, 50.0));
+
+        let path = find_path(&game_map, &start, &goal).unwrap();
+        assert!(path.len() > 1);
+        assert_eq!(goal, *path.last().unwrap());
+
+        let mut from = start;
+        let mut length = 0.0;
+        for waypoint in &path {
+            assert!(is_clear(&game_map, &from, waypoint));
+            assert!(planet.distance_with(waypoint) >= planet.radius + MIN_PLANET_DISTANCE);
+            length += from.distance_with(waypoint);
+            from = *waypoint;
+        }
+        // Shorter than going around a square enclosing the inflated planet.
+        assert!(length < 30.0 + 13.0 * 2.0 + 26.0);
+
+        let clear = Position(20.0, 80.0);
+        assert_eq!(vec![clear], find_path(&game_map, &start, &clear).unwrap());
+
+        // A ship that spawned right next to the planet still goes around it
+        // to reach the far side, but may leave straight away from it.
+        let spawn = Position(38.5, 50.0);
+        assert!(!is_clear(&game_map, &spawn, &goal));
+        assert!(find_path(&game_map, &spawn, &goal).unwrap().len() > 1);
+        assert!(is_clear(&game_map, &spawn, &start));
+    }
+}
@@ -0,0 +1,101 @@
+use std::env;
+
+use rand::{SeedableRng, XorShiftRng};
+
+use hlt::entity::Position;
+use hlt::game_map::GameMap;
+
+/// Environment variable the seed is read from when none is given on the
+/// command line.
+pub const SEED_VAR: &str = "RUSTY_SEED";
+
+/// Seed given as `--seed <n>` or `--seed=<n>`, falling back to `SEED_VAR`.
+pub fn configured_seed() -> Option<u64> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    parse_seed(&args).or_else(|| env::var(SEED_VAR).ok().and_then(|seed| seed.trim().parse().ok()))
+}
+
+fn parse_seed(args: &[String]) -> Option<u64> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = if arg == "--seed" {
+            args.next().map(|value| value.as_str())
+        } else if let Some(value) = arg.strip_prefix("--seed=") {
+            Some(value)
+        } else {
+            continue;
+        };
+        match value.and_then(|value| value.parse().ok()) {
+            Some(seed) => return Some(seed),
+            None => warn!("Ignoring invalid seed {:?}", value),
+        }
+    }
+    None
+}
+
+/// Seed derived from the map, the same for every game on the same map.
+pub fn map_seed(game_map: &GameMap) -> u64 {
+    let (width, height) = game_map.size();
+    let mut hash = fnv(FNV_OFFSET, width as u64);
+    hash = fnv(hash, height as u64);
+    for planet in game_map.all_planets() {
+        let Position(x, y) = planet.position;
+        hash = fnv(hash, planet.id as u64);
+        hash = fnv(hash, x.to_bits());
+        hash = fnv(hash, y.to_bits());
+        hash = fnv(hash, planet.radius.to_bits());
+    }
+    hash
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+fn fnv(mut hash: u64, value: u64) -> u64 {
+    for byte in 0..8 {
+        hash ^= (value >> (byte * 8)) & 0xff;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+/// Generator that produces the same numbers on every platform for a seed.
+pub fn seeded(seed: u64) -> XorShiftRng {
+    // Spread the seed over all four words, xorshift can't start from zero.
+    let mut state = seed;
+    let mut next = || {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    };
+    let (a, b) = (next(), next());
+    let mut words = [a as u32, (a >> 32) as u32, b as u32, (b >> 32) as u32];
+    if words.iter().all(|&word| word == 0) {
+        words[0] = 1;
+    }
+    XorShiftRng::from_seed(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+    use super::{parse_seed, seeded};
+
+    #[test]
+    fn test_seed() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(Some(42), parse_seed(&args(&["--seed", "42"])));
+        assert_eq!(Some(7), parse_seed(&args(&["-v", "--seed=7"])));
+        assert_eq!(None, parse_seed(&args(&["--seed", "x"])));
+        assert_eq!(None, parse_seed(&args(&[])));
+
+        let numbers = |seed| {
+            let mut rng = seeded(seed);
+            (0..4).map(|_| rng.gen::<u32>()).collect::<Vec<_>>()
+        };
+        assert_eq!(numbers(42), numbers(42));
+        assert_ne!(numbers(42), numbers(43));
+    }
+}
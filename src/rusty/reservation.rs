@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+
+use hlt::entity::{DockingStatus, Entity, Planet};
+use hlt::game_map::GameMap;
+
+/// Planets our ships are heading for to dock, so that no more ships go for a
+/// planet than it has spots left.
+#[derive(Debug, Default)]
+pub struct Reservations {
+    claims: HashMap<i32, Vec<i32>>,
+}
+
+impl Reservations {
+    pub fn new() -> Self {
+        Reservations { claims: HashMap::new() }
+    }
+
+    /// Ships holding a claim on the planet.
+    pub fn claimants(&self, planet_id: i32) -> &[i32] {
+        self.claims.get(&planet_id).map_or(&[], |ships| ships.as_slice())
+    }
+
+    pub fn holds(&self, planet_id: i32, ship_id: i32) -> bool {
+        self.claimants(planet_id).contains(&ship_id)
+    }
+
+    /// Docking spots neither taken by ships docked or docking at the planet
+    /// nor claimed.
+    pub fn free_spots(&self, planet: &Planet) -> usize {
+        planet
+            .num_docking_spots
+            .saturating_sub(planet.docked_ships.len() + self.claimants(planet.id).len())
+    }
+
+    /// Test whether the ship holds or can claim a spot on the planet.
+    pub fn is_available(&self, planet: &Planet, ship_id: i32) -> bool {
+        self.holds(planet.id, ship_id) || self.free_spots(planet) > 0
+    }
+
+    /// Claim a spot on the planet, giving up any other claim of the ship.
+    /// Returns false when the planet has no spot left.
+    pub fn claim(&mut self, planet: &Planet, ship_id: i32) -> bool {
+        if self.holds(planet.id, ship_id) {
+            return true;
+        }
+        self.release(ship_id);
+        if self.free_spots(planet) == 0 {
+            return false;
+        }
+        self.claims.entry(planet.id).or_default().push(ship_id);
+        true
+    }
+
+    pub fn release(&mut self, ship_id: i32) {
+        for ships in self.claims.values_mut() {
+            ships.retain(|&id| id != ship_id);
+        }
+        self.claims.retain(|_, ships| !ships.is_empty());
+    }
+
+    /// Bring the claims up to date with a new turn. Claims of ships that have
+    /// reached their planet or are gone are dropped. Claims on planets that
+    /// were destroyed or taken by an enemy are lost, and where fewer spots are
+    /// left than claims the furthest ships lose theirs. Returns the ships that
+    /// lost a claim with its planet.
+    pub fn refresh(&mut self, game_map: &GameMap) -> Vec<(i32, i32)> {
+        let me = game_map.me();
+        let mut lost = Vec::new();
+        for (&planet_id, ships) in &mut self.claims {
+            ships.retain(|&id| {
+                me.get_ship(id)
+                    .is_some_and(|ship| ship.docking_status == DockingStatus::UNDOCKED)
+            });
+
+            let planet = match game_map.get_planet(planet_id) {
+                Some(planet) if claimable(planet, me.id) => planet,
+                _ => {
+                    lost.extend(ships.drain(..).map(|id| (id, planet_id)));
+                    continue;
+                }
+            };
+
+            let room = planet.num_docking_spots.saturating_sub(planet.docked_ships.len());
+            if ships.len() > room {
+                let distance = |id: &i32| me.get_ship(*id).map_or(f64::INFINITY, |ship| ship.distance_with(planet));
+                ships.sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+                lost.extend(ships.drain(room..).map(|id| (id, planet_id)));
+            }
+        }
+        self.claims.retain(|_, ships| !ships.is_empty());
+        lost.sort();
+        lost
+    }
+}
+
+/// Test whether we can dock at the planet, so that a spot on it has to be
+/// claimed first.
+pub fn claimable(planet: &Planet, me: i32) -> bool {
+    planet.owner.is_none_or(|owner| owner == me)
+}
+
+#[cfg(test)]
+mod tests {
+    use hlt::entity::{DockingStatus, Planet, Ship};
+    use hlt::fixture::{docked, game_map, owned, planet, ship, state};
+    use super::Reservations;
+
+    #[test]
+    fn test_reservations() {
+        // Planet 0 has 3 spots with ship 3 docking, planet 1 has 2 spots.
+        let docking = Ship {
+            docking_status: DockingStatus::DOCKING,
+            ..docked(ship(3, 50.0, 42.0), 0)
+        };
+        let game_map = game_map(state(
+            vec![vec![ship(0, 20.0, 50.0), ship(1, 30.0, 50.0), ship(2, 40.0, 50.0), docking]],
+            vec![
+                owned(planet(0, 50.0, 50.0, 5.0), 0, &[3]),
+                Planet { num_docking_spots: 2, ..planet(1, 80.0, 50.0, 5.0) },
+            ],
+        ));
+        let planet = game_map.get_planet(0).unwrap();
+        let mut reservations = Reservations::new();
+
+        assert!(reservations.claim(planet, 0));
+        assert!(reservations.claim(planet, 1));
+        assert!(!reservations.claim(planet, 2));
+        assert!(!reservations.is_available(planet, 2));
+        assert!(reservations.is_available(planet, 1));
+
+        // Moving a claim frees the spot it held.
+        assert!(reservations.claim(game_map.get_planet(1).unwrap(), 0));
+        assert_eq!(&[1], reservations.claimants(0));
+        assert!(reservations.claim(planet, 2));
+        assert_eq!(0, reservations.free_spots(planet));
+        assert_eq!(Vec::<(i32, i32)>::new(), reservations.refresh(&game_map));
+
+        // With more claims than spots the ship furthest away loses its claim.
+        reservations.claims.get_mut(&0).unwrap().push(0);
+        assert_eq!(vec![(0, 0)], reservations.refresh(&game_map));
+        assert_eq!(&[2, 1], reservations.claimants(0));
+
+        // Claims of ships that are gone are dropped without being lost.
+        reservations.claims.get_mut(&0).unwrap().push(9);
+        assert_eq!(Vec::<(i32, i32)>::new(), reservations.refresh(&game_map));
+        assert_eq!(&[2, 1], reservations.claimants(0));
+    }
+}
@@ -0,0 +1,27 @@
+use std::cmp::Ordering;
+
+/// Entry of the open set, ordered so that `BinaryHeap` pops the lowest
+/// estimate first.
+#[derive(Debug, PartialEq)]
+pub struct Node {
+    pub estimate: f64,
+    pub index: usize,
+}
+
+impl Eq for Node {}
+
+impl Ord for Node {
+    fn cmp(&self, other: &Node) -> Ordering {
+        other
+            .estimate
+            .partial_cmp(&self.estimate)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| self.index.cmp(&other.index))
+    }
+}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Node) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
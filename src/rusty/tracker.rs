@@ -0,0 +1,174 @@
+use std::collections::{HashMap, VecDeque};
+
+use hlt::constants::MAX_SPEED;
+use hlt::entity::{Entity, Position, Ship};
+use hlt::game_map::GameMap;
+
+use super::constants::{INTENT_RANGE, INTENT_TOLERANCE, MIN_TRACK_SPEED, TRACK_HISTORY, TRACK_SMOOTHING};
+
+/// What an enemy ship seems to be flying at.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Target {
+    Planet(i32),
+    Ship(i32),
+}
+
+/// Positions of a ship over the last turns, oldest first.
+#[derive(Debug, Default)]
+pub struct Track {
+    positions: VecDeque<Position>,
+}
+
+impl Track {
+    fn record(&mut self, pos: Position) {
+        self.positions.push_back(pos);
+        while self.positions.len() > TRACK_HISTORY {
+            self.positions.pop_front();
+        }
+    }
+
+    /// Distance moved per turn, averaged over the last few turns.
+    pub fn velocity(&self) -> (f64, f64) {
+        let steps = (self.positions.len().max(1) - 1).min(TRACK_SMOOTHING);
+        if steps == 0 {
+            return (0.0, 0.0);
+        }
+        let Position(x1, y1) = self.positions[self.positions.len() - 1 - steps];
+        let Position(x2, y2) = self.positions[self.positions.len() - 1];
+        ((x2 - x1) / steps as f64, (y2 - y1) / steps as f64)
+    }
+
+    pub fn speed(&self) -> f64 {
+        let (vx, vy) = self.velocity();
+        f64::sqrt(vx * vx + vy * vy)
+    }
+
+    /// Direction of travel in degrees, if the ship is moving at all.
+    pub fn heading(&self) -> Option<f64> {
+        if self.speed() < MIN_TRACK_SPEED {
+            return None;
+        }
+        let (vx, vy) = self.velocity();
+        Some((f64::atan2(vy, vx).to_degrees() + 360.0) % 360.0)
+    }
+
+    /// Where the ship will be `turns` turns from now if it keeps its course.
+    pub fn predict(&self, turns: i32) -> Option<Position> {
+        let &Position(x, y) = self.positions.back()?;
+        let (mut vx, mut vy) = self.velocity();
+        let speed = self.speed();
+        if speed > MAX_SPEED as f64 {
+            vx *= MAX_SPEED as f64 / speed;
+            vy *= MAX_SPEED as f64 / speed;
+        }
+        Some(Position(x + vx * turns as f64, y + vy * turns as f64))
+    }
+}
+
+/// Remembers where enemy ships have been to tell where they are going.
+#[derive(Debug, Default)]
+pub struct EnemyTracker {
+    tracks: HashMap<i32, Track>,
+}
+
+impl EnemyTracker {
+    pub fn new() -> Self {
+        EnemyTracker { tracks: HashMap::new() }
+    }
+
+    /// Record the enemy ships of a new turn, forgetting the ones that are gone.
+    pub fn update(&mut self, game_map: &GameMap) {
+        let me = game_map.me().id;
+        let enemies: Vec<&Ship> = game_map
+            .all_players()
+            .iter()
+            .filter(|player| player.id != me)
+            .flat_map(|player| player.all_ships().iter())
+            .collect();
+
+        self.tracks
+            .retain(|id, _| enemies.iter().any(|enemy| enemy.id == *id));
+        for enemy in enemies {
+            self.tracks
+                .entry(enemy.id)
+                .or_default()
+                .record(enemy.position());
+        }
+    }
+
+    pub fn track(&self, ship_id: i32) -> Option<&Track> {
+        self.tracks.get(&ship_id)
+    }
+
+    /// Where the ship is expected `turns` turns from now, where it is now
+    /// when it isn't tracked.
+    pub fn predict(&self, ship: &Ship, turns: i32) -> Position {
+        self.track(ship.id)
+            .and_then(|track| track.predict(turns))
+            .unwrap_or_else(|| ship.position())
+    }
+
+    /// The first of our ships or of the planets not owned by the ship's player
+    /// that lies on its course, within `INTENT_RANGE`.
+    pub fn likely_target(&self, ship_id: i32, game_map: &GameMap) -> Option<Target> {
+        let heading = self.track(ship_id)?.heading()?.to_radians();
+        let (owner, ship) = game_map.get_ship_with_owner(ship_id)?;
+        let ahead = Position::from_origin(ship, heading, INTENT_RANGE);
+
+        // Both queries only return what the course passes within the tolerance
+        let planets = game_map
+            .planets_along(ship, &ahead, INTENT_TOLERANCE)
+            .into_iter()
+            .filter(|planet| planet.owner != Some(owner.id))
+            .map(|planet| (ship.distance_with(planet), Target::Planet(planet.id)));
+        let ships = game_map
+            .ships_along(ship, &ahead, INTENT_TOLERANCE)
+            .into_iter()
+            .filter(|other| game_map.me().get_ship(other.id).is_some())
+            .map(|other| (ship.distance_with(other), Target::Ship(other.id)));
+
+        planets
+            .chain(ships)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, target)| target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hlt::entity::Position;
+    use hlt::fixture::{game, planet, ship, state};
+    use super::{EnemyTracker, Target};
+
+    #[test]
+    fn test_tracker() {
+        // Enemy ship 1 flies right at 4 per turn towards planet 0 at (60, 20),
+        // enemy ship 2 stays put.
+        let frame = |x: f64| {
+            state(
+                vec![vec![ship(0, 10.0, 80.0)], vec![ship(1, x, 20.0), ship(2, 50.0, 80.0)]],
+                vec![planet(0, 60.0, 20.0, 5.0)],
+            )
+        };
+        let mut game = game(100, 100, &[frame(10.0), frame(14.0), frame(18.0)]);
+        let mut tracker = EnemyTracker::new();
+        let mut game_map = game.update_map().unwrap();
+        tracker.update(&game_map);
+        assert_eq!(None, tracker.track(1).unwrap().heading());
+        for _ in 0..2 {
+            game_map = game.update_map().unwrap();
+            tracker.update(&game_map);
+        }
+
+        let track = tracker.track(1).unwrap();
+        assert_eq!((4.0, 0.0), track.velocity());
+        assert_eq!(Some(0.0), track.heading());
+        assert_eq!(Position(30.0, 20.0), tracker.predict(game_map.get_ship(1).unwrap(), 3));
+        assert_eq!(Some(Target::Planet(0)), tracker.likely_target(1, &game_map));
+
+        // A ship that doesn't move has no course.
+        assert_eq!(Position(50.0, 80.0), tracker.predict(game_map.get_ship(2).unwrap(), 2));
+        assert_eq!(None, tracker.likely_target(2, &game_map));
+        assert!(tracker.track(0).is_none());
+    }
+}
@@ -1,289 +1,232 @@
-use std::f64::consts::PI;
-use std::collections::vec_deque::VecDeque;
-use std::cmp::min;
-use std::cmp::Ordering::{Less, Equal, Greater};
-use hlt::constants::{MAX_SPEED, DOCK_RADIUS};
+use std::mem;
+
 use hlt::command::Command;
-use hlt::entity::{Entity, Ship, Position, Planet};
+use hlt::entity::{DockingStatus, Ship};
 use hlt::game_map::GameMap;
+use hlt::navigation::NavigationPlan;
+
+use super::constants::{NAVIGATION_ATTEMPTS, REDUCED_NAVIGATION_ATTEMPTS};
+use super::behavior::{Behavior, BehaviorRegistry, Context, Navigator, UnitBehavior};
+use super::budget::{Pressure, TurnBudget};
+use super::events::FleetEvent;
+use super::pathfinding::Route;
 
-use super::constants::MIN_PLANET_DISTANCE;
-use super::behavior::Behavior;
+/// Most role changes a unit goes through in a single update, in case two
+/// roles keep handing the unit to each other.
+const MAX_TRANSITIONS: usize = 4;
 
 #[derive(Debug)]
 pub struct Unit {
     pub ship_id: i32,
-    pub behavior: Behavior,
-    pub target: Option<i32>,
-    target_queue: VecDeque<Position>,
+    behavior: Box<dyn UnitBehavior>,
+    route: Route,
+    last_command: Option<Command>,
+    docking_status: DockingStatus,
 }
 
 impl Unit {
-    pub fn new(ship: &Ship, behavior: Behavior) -> Self {
-        Unit {
-            behavior: behavior,
+    /// A unit for the ship, entering its first role like any later one.
+    pub fn new(ship: &Ship, kind: Behavior, ctx: &Context, registry: &BehaviorRegistry) -> Self {
+        let mut unit = Unit {
             ship_id: ship.id,
-            target: None,
-            target_queue: VecDeque::new(),
-        }
+            behavior: registry.create(kind),
+            route: Route::new(),
+            last_command: None,
+            docking_status: ship.docking_status,
+        };
+        unit.behavior.on_enter(ship, ctx);
+        unit
+    }
+
+    /// The role the unit currently plays.
+    pub fn behavior(&self) -> Behavior {
+        self.behavior.kind()
     }
 
     pub fn to_string(&self, game_map: &GameMap) -> String {
         let ship = game_map.me().get_ship(self.ship_id);
-        format!("Unit(\n\tbehavior={}, \n\ttarget_id={}, \n\ttarget_pos={}, ship={})",
-               self.behavior,
-               format!("{}",
-                       self.target
-                       .and_then(|id| Some(id.to_string()))
-                       .unwrap_or("None".to_string())),
-               format!("{:?}", self.target_queue),
-               ship.and_then(|ship| Some(format!("{}", ship))).unwrap_or("None".to_string()),
+        format!(
+            "Unit(\n\tbehavior={:?}, \n\troute={:?}, ship={})",
+            self.behavior,
+            self.route,
+            ship.map_or("None".to_string(), |ship| ship.to_string()),
         )
     }
 
+    /// Hand the unit over to another role.
+    pub fn switch(&mut self, ship: &Ship, kind: Behavior, ctx: &Context, registry: &BehaviorRegistry) {
+        info!("Ship {}: {} -> {}", self.ship_id, self.behavior(), kind);
+        self.behavior.on_exit(ship, ctx);
+        self.behavior = registry.create(kind);
+        self.route.clear();
+        self.behavior.on_enter(ship, ctx);
+    }
+
+    /// Event for the ship finishing docking or undocking since the last call.
+    pub fn track_docking(&mut self, ship: &Ship) -> Option<FleetEvent> {
+        let previous = mem::replace(&mut self.docking_status, ship.docking_status);
+        if previous == ship.docking_status {
+            return None;
+        }
+        match ship.docking_status {
+            DockingStatus::DOCKED => ship.docked_planet.map(|planet| {
+                FleetEvent::Docked { ship: ship.id, planet }
+            }),
+            DockingStatus::UNDOCKED => Some(FleetEvent::Undocked(ship.id)),
+            _ => None,
+        }
+    }
+
+    /// Let the behavior react to something that happened to the ship.
+    pub fn notify(&mut self, event: &FleetEvent, ctx: &Context) {
+        self.behavior.on_event(event, ctx);
+    }
+
     /// Updates the units target if necessary
-    pub fn update(&mut self, ship: &Ship, game_map: &GameMap) {
+    pub fn update(&mut self, ship: &Ship, ctx: &Context, registry: &BehaviorRegistry) {
         if ship.id != self.ship_id {
-            panic!(format!(
+            panic!(
                 "Mismatch ship and unit id: Ship id == {}, Unit id == {}",
                 ship.id,
                 self.ship_id
-            ))
+            )
         }
 
         if ship.is_docked() {
-            self.target = None;
-            self.target_queue.clear();
+            self.route.clear();
             debug!("Ship {}: Already docked or docking. Nothing to do", self.ship_id);
             return;
         }
 
-        match self.behavior {
-            Behavior::Settler => self.update_settler(&ship, game_map),
-            Behavior::Raider => self.update_raider(&ship, game_map),
-            _ => (),
+        for _ in 0..MAX_TRANSITIONS {
+            match self.behavior.update(ship, ctx) {
+                Some(next) => self.switch(ship, next, ctx, registry),
+                None => return,
+            }
         }
+        warn!("Ship {}: too many role changes, staying {}", self.ship_id, self.behavior());
+    }
+
+    /// Order in which units are executed, lower goes first so that important
+    /// units still get a command when the turn runs short on time.
+    pub fn priority(&self) -> i32 {
+        self.behavior.priority()
     }
 
     /// Execute the logic for this units turn
-    pub fn execute(&mut self, ship: &Ship, game_map: &GameMap) -> Option<Command> {
+    pub fn execute(
+        &mut self,
+        ship: &Ship,
+        ctx: &Context,
+        plan: &mut NavigationPlan,
+        budget: &TurnBudget,
+    ) -> Option<Command> {
         if ship.id != self.ship_id {
-            panic!(format!(
+            panic!(
                 "Mismatch ship and unit id: Ship id == {}, Unit id == {}",
                 ship.id,
                 self.ship_id
-            ))
+            )
         }
 
-        if ship.is_docked() {
-            debug!("Ship {}: docked with {:?}", self.ship_id, self.target);
-            return Some(Command::Nop);
-        }
-
-        if let Some(target) = self.target.and_then(|id| game_map.get_planet(id)) {
-            match self.behavior {
-                Behavior::Raider => if ship.can_dock(target) {
-                    debug!("Ship {}: docking with {:?}", self.ship_id, self.target);
-                    return Some(ship.dock(target))
-                },
-                _ => if ship.can_dock(target) {
-                    debug!("Ship {}: docking with {:?}", self.ship_id, self.target);
-                    return Some(ship.dock(target))
-                },
-            }
-
-            let pos = match self.behavior {
-                Behavior::Raider =>{
-                    if target.owner.is_none() {
-                        ship.closest_point_to(target, MIN_PLANET_DISTANCE)
-                    } else {
-                        ship.furthest_point_to(target, MIN_PLANET_DISTANCE)
-                    }
+        if budget.pressure() >= Pressure::Critical && !ship.is_docked() {
+            if let Some(cmd @ Command::Thrust(_, _, _)) = self.last_command {
+                if plan.try_reserve(ship, &cmd, ctx.game_map) {
+                    debug!("Ship {}: short on time, repeating {}", self.ship_id, cmd);
+                    return Some(cmd);
                 }
-                _ => ship.closest_point_to(target, MIN_PLANET_DISTANCE)
-            };
-            return ship.navigate_to(&pos, game_map);
-            // if let Some(sub_target) = self.target_queue.pop_front() {
-            //     if sub_target.distance_with(ship) > MAX_SPEED as f64 {
-            //         self.target_queue.push_front(sub_target);
-            //     }
-
-            //     if sub_target.position() == target.position() {
-            //         let pos = match self.behavior {
-            //             Behavior::Raider => if target.owner.is_none() {
-            //                 ship.closest_point_to(target, MIN_PLANET_DISTANCE)
-            //             } else {
-            //                 ship.furthest_point_to(target, MIN_PLANET_DISTANCE)
-            //             },
-            //             _ => ship.closest_point_to(target, MIN_PLANET_DISTANCE),
-            //         };
-            //         return ship.navigate_to(&pos, game_map, Some(MIN_PLANET_DISTANCE))
-            //     } else {
-            //         return ship.navigate_to(&sub_target, game_map, Some(MIN_PLANET_DISTANCE))
-            //     }
+                // No time to look for another way
+                debug!("Ship {}: short on time and {} is blocked, waiting", self.ship_id, cmd);
+                self.last_command = Some(Command::Nop);
+                return self.last_command;
+            }
         }
-        return None
-    }
-
-    fn update_route(& mut self, game_map: &GameMap) {
-        let ship = game_map.get_ship(self.ship_id).expect(
-            "Bot updating a unit that no longer exists",
-        );
-        let target = self.target.and_then(|id| game_map.get_planet(id)).expect(
-            "Target updated to an invalid planet",
-        );
 
-        let mut cur = ship.position();
-        debug!("Ship {}: Updating route for target {}", ship.id, target);
-        loop {
-            // Possible conflicting planet
-            if let Some(conflict) = game_map.planet_between(&cur, target, MIN_PLANET_DISTANCE) {
-                if conflict == target {
-                    self.target_queue.push_back(target.position);
-                    break;
-                }
-
-                trace!("Ship {}: conflict detected between {} and {};", ship.id, cur, conflict.position());
-                let angle = cur.angle_with(conflict);
-                let distance = cur.distance_with(conflict);
-                let radius = conflict.radius + MIN_PLANET_DISTANCE;
-                let Position(planet_x, planet_y) = conflict.position();
-                let pos = {
-                    let p1 = {
-                        let x = radius * f64::cos(angle + PI / 2.0) + planet_x;
-                        let y = radius * f64::sin(angle + PI / 2.0) + planet_y;
-                        Position(x, y)
-                    };
-                    let p2 = {
-                        let x = radius * f64::cos(angle - PI / 2.0) + planet_x;
-                        let y = radius * f64::sin(angle - PI / 2.0) + planet_y;
-                        Position(x, y)
-                    };
-                    if target.distance_with(&p1) < target.distance_with(&p2) {
-                        p1
-                    } else {
-                        p2
-                    }
-                };
-                let middle = {
-                    let Position(cur_x, cur_y) = cur.position();
-                    let offset =  {
-                        if radius < distance {
-                            let sign = (cur.angle_with(&pos) - cur.angle_with(conflict)).signum();
-                            sign * f64::asin(radius / distance)
-                        } else {
-                            PI / 2.0
-                        }
-                    };
-                    let distance = MAX_SPEED as f64;
-                    let x = distance * f64::cos(angle + offset) + cur_x;
-                    let y = distance * f64::sin(angle + offset) + cur_y;
-                    Position(x, y)
-                };
-
-                self.target_queue.push_back(middle);
-                self.target_queue.push_back(pos);
-                cur = pos;
+        let cmd = if ship.is_docked() {
+            debug!("Ship {}: docked with {:?}", self.ship_id, ship.docked_planet);
+            Some(Command::Nop)
+        } else {
+            let attempts = if budget.pressure() == Pressure::Normal {
+                NAVIGATION_ATTEMPTS
             } else {
-                trace!("No conflict found");
-                self.target_queue.push_back(target.position);
-                break;
-            }
-        }
-        debug!("Ship {}: Route calculated {:?}", self.ship_id, self.target_queue)
+                REDUCED_NAVIGATION_ATTEMPTS
+            };
+            let mut nav = Navigator::new(plan, &mut self.route, attempts);
+            self.behavior.execute(ship, ctx, &mut nav)
+        };
+        self.last_command = cmd;
+        cmd
     }
+}
 
-    fn update_settler(&mut self, ship: &Ship, game_map: &GameMap) {
-        debug!("Ship {}: updating with settler settings", self.ship_id);
-        let me = game_map.me().id;
-
-        let need_new_target = self.target
-            .and_then(|target| game_map.get_planet(target))
-            .and_then(|planet| Some(planet.owner == Some(me) && planet.is_full()))
-            .unwrap_or(true);
+#[cfg(test)]
+mod tests {
+    use hlt::command::Command;
+    use hlt::entity::Ship;
+    use hlt::fixture::{game_map, ship, state};
+    use rusty::behavior::{Behavior, BehaviorRegistry, Context, Navigator, TestContext,
+                          UnitBehavior};
+    use super::Unit;
+
+    /// A settler that only tells whether it was entered, through its priority.
+    #[derive(Debug, Default)]
+    struct Probe {
+        entered: bool,
+    }
 
-        if !need_new_target {
-            return;
+    impl UnitBehavior for Probe {
+        fn kind(&self) -> Behavior {
+            Behavior::Settler
         }
 
-        // find a new target
-        let planet_iter = game_map.all_planets().iter();
-        let target = planet_iter
-            .filter(|planet| if planet.is_dead() {
-                false
-            } else {
-                planet.owner == Some(me) && !planet.is_full() || planet.owner != Some(me)
-            })
-            .min_by(|&a, &b| {
-                let dist_a = ship.distance_with(a);
-                let dist_b = ship.distance_with(b);
-                if dist_a < dist_b {
-                    Less
-                } else if dist_a == dist_b {
-                    Equal
-                } else {
-                    Greater
-                }
-            })
-            .and_then(|planet| Some(planet.id));
-
-        if target.is_none() {
-            self.behavior = Behavior::Raider;
-            self.target = None;
-            self.target_queue.clear();
-            self.update(ship, game_map);
-        } else {
-            self.target = target;
-            self.update_route(game_map);
+        fn on_enter(&mut self, _ship: &Ship, _ctx: &Context) {
+            self.entered = true;
         }
-    }
 
-    fn update_raider(&mut self, ship: &Ship, game_map: &GameMap) {
-        debug!("Ship {}: updating with raider settings", self.ship_id);
-        let me = game_map.me().id;
-
-        let need_new_target = self.target
-            .and_then(|target| game_map.all_planets().get(target as usize))
-            .and_then(|planet| Some(planet.owner == Some(me)))
-            .unwrap_or(true);
+        fn update(&mut self, _ship: &Ship, _ctx: &Context) -> Option<Behavior> {
+            None
+        }
 
-        if !need_new_target {
-            return;
+        fn execute(
+            &mut self,
+            _ship: &Ship,
+            _ctx: &Context,
+            _nav: &mut Navigator,
+        ) -> Option<Command> {
+            None
         }
 
-        let planet_iter = game_map.all_planets().iter();
-        let planet = planet_iter
-            .filter(|planet| if let Some(owner) = planet.owner {
-                owner != me
-            } else {
-                true
-            })
-            .min_by(|&a, &b| {
-                let dist_a = ship.distance_with(a);
-                let dist_b = ship.distance_with(b);
+        fn priority(&self) -> i32 {
+            if self.entered { 0 } else { 1 }
+        }
+    }
 
-                if a.owner.is_none() && b.owner.is_some() {
-                    Less
-                } else if a.owner != Some(me) && b.owner == Some(me) {
-                    Less
-                } else if b.owner.is_none() && a.owner.is_some() {
-                    Greater
-                } else if b.owner != Some(me) && a.owner == Some(me) {
-                    Greater
-                } else {
-                    if dist_a < dist_b {
-                        Less
-                    } else if dist_a == dist_b {
-                        Equal
-                    } else {
-                        Greater
-                    }
-                }
-            });
+    #[test]
+    fn test_transitions() {
+        // Our only ship 0 and no planets: the settler has nowhere to go and
+        // hands the unit over to the raider.
+        let game_map = game_map(state(vec![vec![ship(0, 10.0, 50.0)], vec![ship(1, 90.0, 50.0)]], vec![]));
+        let parts = TestContext::new(&game_map);
+        let ctx = parts.context(&game_map);
+        let registry = BehaviorRegistry::default();
+        let ship = game_map.get_ship(0).unwrap();
+
+        let mut unit = Unit::new(ship, Behavior::Settler, &ctx, &registry);
+        assert_eq!(0, unit.priority());
+        unit.update(ship, &ctx, &registry);
+        assert_eq!(Behavior::Raider, unit.behavior());
+    }
 
-        if let Some(target) = planet {
-            self.target = Some(target.id);
-            self.update_route(game_map);
-        }
+    #[test]
+    fn test_enter_first_role() {
+        let game_map = game_map(state(vec![vec![ship(0, 10.0, 50.0)], vec![]], vec![]));
+        let parts = TestContext::new(&game_map);
+        let ctx = parts.context(&game_map);
+        let mut registry = BehaviorRegistry::default();
+        assert!(registry.register(Behavior::Settler, || Box::new(Probe::default())));
+        let ship = game_map.get_ship(0).unwrap();
+
+        let unit = Unit::new(ship, Behavior::Settler, &ctx, &registry);
+        assert_eq!(0, unit.priority());
     }
 }